@@ -1,8 +1,9 @@
-use std::ops::Deref;
+use std::{io::Cursor, ops::Deref};
 
 use binrw::binrw;
 
 use super::Bytes;
+use crate::BorrowRead;
 
 /// Create an [`Ascii`] string from a literal in _const_-context.
 #[doc(hidden)]
@@ -81,6 +82,22 @@ impl<'b> Ascii<'b> {
     }
 }
 
+impl<'b> BorrowRead<'b> for Ascii<'b> {
+    fn borrow_read(cursor: &mut Cursor<&'b [u8]>) -> binrw::BinResult<Self> {
+        let pos = cursor.position();
+        let bytes = Bytes::borrow_read(cursor)?;
+
+        if bytes.is_ascii() {
+            Ok(Self(bytes))
+        } else {
+            Err(binrw::Error::AssertFail {
+                pos,
+                message: "the string wasn't ASCII-formatted".into(),
+            })
+        }
+    }
+}
+
 impl std::fmt::Debug for Ascii<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("Ascii").field(&&**self).finish()
@@ -1,7 +1,9 @@
-use std::ops::Deref;
+use std::{io::Cursor, ops::Deref};
 
 use binrw::{BinRead, BinWrite};
 
+use crate::BorrowRead;
+
 #[cfg(feature = "zeroize")]
 use zeroize::Zeroize;
 
@@ -119,6 +121,23 @@ impl BinRead for Bytes<'_> {
     }
 }
 
+impl<'b> BorrowRead<'b> for Bytes<'b> {
+    fn borrow_read(cursor: &mut Cursor<&'b [u8]>) -> binrw::BinResult<Self> {
+        let size = u32::borrow_read(cursor)? as usize;
+
+        let buf: &'b [u8] = cursor.get_ref();
+        let start = cursor.position() as usize;
+        let value = buf
+            .get(start..)
+            .and_then(|rest| rest.get(..size))
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?;
+
+        cursor.set_position((start + size) as u64);
+
+        Ok(Self::borrowed(value))
+    }
+}
+
 impl BinWrite for Bytes<'_> {
     type Args<'a> = ();
 
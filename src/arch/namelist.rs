@@ -7,12 +7,12 @@ use super::Ascii;
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4251#section-5>.
 #[binrw]
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct NameList<'b>(pub Ascii<'b>);
 
 impl NameList<'_> {
     /// Retrieve the first name from `self` that is also in `other`.
-    pub fn preferred_in(&self, other: &Self) -> Option<Ascii<'_>> {
+    pub fn preferred_in(&self, other: &NameList<'_>) -> Option<Ascii<'_>> {
         self.into_iter()
             .find(|this| other.into_iter().any(|other| this == &other))
     }
@@ -1,8 +1,9 @@
-use std::ops::Deref;
+use std::{io::Cursor, ops::Deref};
 
 use binrw::binrw;
 
 use super::Bytes;
+use crate::BorrowRead;
 
 /// A `string` as defined in the SSH protocol, restricted to valid **UTF-8**.
 ///
@@ -34,6 +35,22 @@ impl<'b> Utf8<'b> {
     }
 }
 
+impl<'b> BorrowRead<'b> for Utf8<'b> {
+    fn borrow_read(cursor: &mut Cursor<&'b [u8]>) -> binrw::BinResult<Self> {
+        let pos = cursor.position();
+        let bytes = Bytes::borrow_read(cursor)?;
+
+        if std::str::from_utf8(&bytes).is_ok() {
+            Ok(Self(bytes))
+        } else {
+            Err(binrw::Error::AssertFail {
+                pos,
+                message: "the string wasn't UTF-8-formatted".into(),
+            })
+        }
+    }
+}
+
 impl std::fmt::Debug for Utf8<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("Utf8").field(&&**self).finish()
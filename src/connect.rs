@@ -1,11 +1,11 @@
 //! Messages involved in the SSH's **connect** (`SSH-CONNECT`) part of the protocol,
 //! as defined in the [RFC 4254](https://datatracker.ietf.org/doc/html/rfc4254).
 
-use std::num::NonZeroU32;
+use std::{io::Cursor, num::NonZeroU32};
 
 use binrw::binrw;
 
-use crate::arch;
+use crate::{arch, packet::read_magic, BorrowRead};
 
 /// The `SSH_MSG_GLOBAL_REQUEST` message.
 ///
@@ -288,6 +288,17 @@ pub struct ChannelData<'b> {
     pub data: arch::Bytes<'b>,
 }
 
+impl<'b> BorrowRead<'b> for ChannelData<'b> {
+    fn borrow_read(cursor: &mut Cursor<&'b [u8]>) -> binrw::BinResult<Self> {
+        read_magic(cursor, 94)?;
+
+        Ok(Self {
+            recipient_channel: BorrowRead::borrow_read(cursor)?,
+            data: BorrowRead::borrow_read(cursor)?,
+        })
+    }
+}
+
 /// The `SSH_MSG_CHANNEL_EXTENDED_DATA` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.2>.
@@ -305,6 +316,18 @@ pub struct ChannelExtendedData<'b> {
     pub data: arch::Bytes<'b>,
 }
 
+impl<'b> BorrowRead<'b> for ChannelExtendedData<'b> {
+    fn borrow_read(cursor: &mut Cursor<&'b [u8]>) -> binrw::BinResult<Self> {
+        read_magic(cursor, 95)?;
+
+        Ok(Self {
+            recipient_channel: BorrowRead::borrow_read(cursor)?,
+            data_type: BorrowRead::borrow_read(cursor)?,
+            data: BorrowRead::borrow_read(cursor)?,
+        })
+    }
+}
+
 /// The `SSH_MSG_CHANNEL_EOF` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4254#section-5.3>.
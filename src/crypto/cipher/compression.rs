@@ -0,0 +1,274 @@
+#[cfg(feature = "zlib")]
+use flate2::{Compress, Decompress, FlushCompress, FlushDecompress};
+
+use crate::Error;
+
+/// The _compression algorithms_ implemented by this crate.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.2>.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// The `none` compression algorithm, leaving the payload untouched.
+    #[default]
+    None,
+
+    /// The `zlib` compression algorithm,
+    /// as defined in [RFC4253 section 6.2](https://datatracker.ietf.org/doc/html/rfc4253#section-6.2).
+    #[cfg(feature = "zlib")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zlib")))]
+    Zlib,
+
+    /// The `zlib@openssh.com` compression algorithm, identical to `zlib`
+    /// but only active once the user has been successfully authenticated.
+    #[cfg(feature = "zlib")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zlib")))]
+    ZlibOpenssh,
+}
+
+impl Compression {
+    /// All the [`Compression`] algorithms, in order of preference.
+    pub const ALL: &'static [Self] = &[
+        Self::None,
+        #[cfg(feature = "zlib")]
+        Self::ZlibOpenssh,
+        #[cfg(feature = "zlib")]
+        Self::Zlib,
+    ];
+
+    /// Get the [`Compression`] algorithm's SSH identifier.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            #[cfg(feature = "zlib")]
+            Self::Zlib => "zlib",
+            #[cfg(feature = "zlib")]
+            Self::ZlibOpenssh => "zlib@openssh.com",
+        }
+    }
+
+    /// Get the [`Compression`] algorithm from its SSH identifier, if implemented.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|algorithm| algorithm.name() == name)
+            .copied()
+    }
+
+    /// Whether the [`Compression`] algorithm only activates after user authentication.
+    pub const fn is_delayed(&self) -> bool {
+        match self {
+            Self::None => false,
+            #[cfg(feature = "zlib")]
+            Self::Zlib => false,
+            #[cfg(feature = "zlib")]
+            Self::ZlibOpenssh => true,
+        }
+    }
+
+    pub(super) fn init(&self) -> State {
+        match self {
+            Self::None => State::None,
+            #[cfg(feature = "zlib")]
+            Self::Zlib | Self::ZlibOpenssh => State::Zlib {
+                active: !self.is_delayed(),
+                compress: None,
+                decompress: None,
+            },
+        }
+    }
+}
+
+/// The streaming state of a [`Compression`] algorithm, for a single direction of the transport.
+pub(super) enum State {
+    None,
+
+    #[cfg(feature = "zlib")]
+    Zlib {
+        active: bool,
+
+        // Both contexts are lazily allocated, as only one is used per direction
+        compress: Option<Box<Compress>>,
+        decompress: Option<Box<Decompress>>,
+    },
+}
+
+impl State {
+    /// Activate a delayed compression, which is a no-op for the other algorithms.
+    pub fn activate(&mut self) {
+        match self {
+            Self::None => (),
+            #[cfg(feature = "zlib")]
+            Self::Zlib { active, .. } => *active = true,
+        }
+    }
+
+    /// Compress the `buf`, flushing the stream so that the peer can decompress it entirely.
+    pub fn compress(&mut self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        self.compress_into(buf, &mut out)?;
+
+        Ok(out)
+    }
+
+    /// Compress the `buf` by appending it to `out`, see [`State::compress`].
+    pub fn compress_into(&mut self, buf: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+        match self {
+            #[cfg(feature = "zlib")]
+            Self::Zlib {
+                active: true,
+                compress,
+                ..
+            } => {
+                let compress = compress
+                    .get_or_insert_with(|| Box::new(Compress::new(Default::default(), true)));
+
+                let start = compress.total_in();
+                out.reserve(buf.len() + 64);
+                loop {
+                    if out.len() == out.capacity() {
+                        out.reserve(buf.len().max(64));
+                    }
+
+                    let consumed = (compress.total_in() - start) as usize;
+                    compress
+                        .compress_vec(&buf[consumed..], out, FlushCompress::Partial)
+                        .map_err(|_| Error::Compression("the payload compression failed"))?;
+
+                    // The flush is complete when the output wasn't filled entirely
+                    if (compress.total_in() - start) as usize == buf.len()
+                        && out.len() < out.capacity()
+                    {
+                        break Ok(());
+                    }
+                }
+            }
+            _ => {
+                out.extend_from_slice(buf);
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Decompress the `buf`, bounding the decompressed size to `max` bytes.
+    #[cfg_attr(not(feature = "zlib"), allow(unused_variables))]
+    pub fn decompress(&mut self, buf: Vec<u8>, max: usize) -> Result<Vec<u8>, Error> {
+        match self {
+            #[cfg(feature = "zlib")]
+            Self::Zlib {
+                active: true,
+                decompress,
+                ..
+            } => {
+                let decompress = decompress.get_or_insert_with(|| Box::new(Decompress::new(true)));
+
+                let start = decompress.total_in();
+                let mut out = Vec::with_capacity(buf.len() * 2);
+                loop {
+                    if out.len() == out.capacity() {
+                        out.reserve(buf.len().max(64));
+                    }
+
+                    let (consumed, produced) = (decompress.total_in(), out.len());
+                    decompress
+                        .decompress_vec(
+                            &buf[(consumed - start) as usize..],
+                            &mut out,
+                            FlushDecompress::Sync,
+                        )
+                        .map_err(|_| Error::Compression("the payload decompression failed"))?;
+
+                    if out.len() > max {
+                        return Err(Error::Compression(
+                            "the decompressed payload exceeds the maximum packet size",
+                        ));
+                    }
+
+                    let exhausted = (decompress.total_in() - start) as usize == buf.len();
+                    let stalled = decompress.total_in() == consumed && out.len() == produced;
+
+                    if exhausted && out.len() < out.capacity() {
+                        break Ok(out);
+                    }
+
+                    // No progress while input remains means the stream is corrupted, or has ended
+                    if stalled {
+                        if exhausted {
+                            break Ok(out);
+                        }
+
+                        return Err(Error::Compression("the payload decompression stalled"));
+                    }
+                }
+            }
+            _ => Ok(buf),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "zlib"))]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::PACKET_MAX_SIZE;
+
+    #[rstest]
+    #[case(Compression::Zlib)]
+    #[case(Compression::ZlibOpenssh)]
+    fn it_roundtrips_streams(#[case] compression: Compression) {
+        let (mut sealing, mut opening) = (compression.init(), compression.init());
+
+        let payloads = [vec![0x42; 10_000], b"payload".to_vec(), vec![]];
+        for (i, payload) in payloads.iter().enumerate() {
+            // Delayed compression gets activated after the first packet
+            if i == 1 {
+                sealing.activate();
+                opening.activate();
+            }
+
+            let compressed = sealing.compress(payload).unwrap();
+            if i == 0 {
+                assert_eq!(compressed.len() < payload.len(), !compression.is_delayed());
+            }
+
+            assert_eq!(
+                &opening.decompress(compressed, PACKET_MAX_SIZE).unwrap(),
+                payload
+            );
+        }
+    }
+
+    #[test]
+    fn it_rejects_trailing_data() {
+        let mut compress = Compress::new(Default::default(), true);
+        let mut finished = Vec::with_capacity(1024);
+        compress
+            .compress_vec(&[0x42; 64], &mut finished, FlushCompress::Finish)
+            .unwrap();
+
+        // Anything past the end of the zlib stream can't be consumed
+        finished.extend_from_slice(b"trailing");
+
+        let mut opening = Compression::Zlib.init();
+        assert!(matches!(
+            opening.decompress(finished, PACKET_MAX_SIZE),
+            Err(Error::Compression(_))
+        ));
+    }
+
+    #[test]
+    fn it_rejects_decompression_bombs() {
+        let (mut sealing, mut opening) = (Compression::Zlib.init(), Compression::Zlib.init());
+
+        let bomb = sealing.compress(&vec![0; PACKET_MAX_SIZE * 4]).unwrap();
+        assert!(bomb.len() < PACKET_MAX_SIZE);
+
+        assert!(matches!(
+            opening.decompress(bomb, PACKET_MAX_SIZE),
+            Err(Error::Compression(_))
+        ));
+    }
+}
@@ -0,0 +1,189 @@
+#[cfg(feature = "aes-ctr")]
+use ctr::cipher::{KeyIvInit, StreamCipher};
+
+use crate::Error;
+
+/// The _encryption algorithms_ implemented by this crate.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.3>.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encryption {
+    /// The `none` encryption algorithm, leaving the data unencrypted.
+    #[default]
+    None,
+
+    /// The `aes128-ctr` encryption algorithm,
+    /// as defined in [RFC4344 section 4](https://datatracker.ietf.org/doc/html/rfc4344#section-4).
+    #[cfg(feature = "aes-ctr")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "aes-ctr")))]
+    Aes128Ctr,
+
+    /// The `aes192-ctr` encryption algorithm,
+    /// as defined in [RFC4344 section 4](https://datatracker.ietf.org/doc/html/rfc4344#section-4).
+    #[cfg(feature = "aes-ctr")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "aes-ctr")))]
+    Aes192Ctr,
+
+    /// The `aes256-ctr` encryption algorithm,
+    /// as defined in [RFC4344 section 4](https://datatracker.ietf.org/doc/html/rfc4344#section-4).
+    #[cfg(feature = "aes-ctr")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "aes-ctr")))]
+    Aes256Ctr,
+}
+
+impl Encryption {
+    /// All the [`Encryption`] algorithms, in order of preference.
+    pub const ALL: &'static [Self] = &[
+        #[cfg(feature = "aes-ctr")]
+        Self::Aes256Ctr,
+        #[cfg(feature = "aes-ctr")]
+        Self::Aes192Ctr,
+        #[cfg(feature = "aes-ctr")]
+        Self::Aes128Ctr,
+        Self::None,
+    ];
+
+    /// Get the [`Encryption`] algorithm's SSH identifier.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes128Ctr => "aes128-ctr",
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes192Ctr => "aes192-ctr",
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes256Ctr => "aes256-ctr",
+        }
+    }
+
+    /// Get the [`Encryption`] algorithm from it's SSH identifier, if implemented.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|algorithm| algorithm.name() == name)
+            .copied()
+    }
+
+    /// The size of the [`Encryption`] algorithm's block.
+    pub const fn block_size(&self) -> usize {
+        match self {
+            Self::None => 8,
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes128Ctr | Self::Aes192Ctr | Self::Aes256Ctr => 16,
+        }
+    }
+
+    /// The size of the [`Encryption`] algorithm's key.
+    pub const fn key_size(&self) -> usize {
+        match self {
+            Self::None => 0,
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes128Ctr => 16,
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes192Ctr => 24,
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes256Ctr => 32,
+        }
+    }
+
+    /// The size of the [`Encryption`] algorithm's initialization vector.
+    pub const fn iv_size(&self) -> usize {
+        match self {
+            Self::None => 0,
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes128Ctr | Self::Aes192Ctr | Self::Aes256Ctr => 16,
+        }
+    }
+
+    pub(super) fn init(&self, key: &[u8], iv: &[u8]) -> Result<State, Error> {
+        if key.len() != self.key_size() || iv.len() != self.iv_size() {
+            return Err(Error::KeyLength(self.name()));
+        }
+
+        Ok(match self {
+            Self::None => State::None,
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes128Ctr => State::Aes128Ctr(Box::new(KeyIvInit::new(key.into(), iv.into()))),
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes192Ctr => State::Aes192Ctr(Box::new(KeyIvInit::new(key.into(), iv.into()))),
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes256Ctr => State::Aes256Ctr(Box::new(KeyIvInit::new(key.into(), iv.into()))),
+        })
+    }
+}
+
+/// The keyed state of an [`Encryption`] algorithm.
+pub(super) enum State {
+    None,
+
+    #[cfg(feature = "aes-ctr")]
+    Aes128Ctr(Box<ctr::Ctr128BE<aes::Aes128>>),
+
+    #[cfg(feature = "aes-ctr")]
+    Aes192Ctr(Box<ctr::Ctr128BE<aes::Aes192>>),
+
+    #[cfg(feature = "aes-ctr")]
+    Aes256Ctr(Box<ctr::Ctr128BE<aes::Aes256>>),
+}
+
+impl State {
+    /// Apply the keystream to the `buf`, which both encrypts and decrypts it.
+    #[cfg_attr(not(feature = "aes-ctr"), allow(unused_variables))]
+    pub(super) fn apply(&mut self, buf: &mut [u8]) {
+        match self {
+            Self::None => (),
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes128Ctr(cipher) => cipher.apply_keystream(buf),
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes192Ctr(cipher) => cipher.apply_keystream(buf),
+            #[cfg(feature = "aes-ctr")]
+            Self::Aes256Ctr(cipher) => cipher.apply_keystream(buf),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "aes-ctr"))]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::crypto::cipher::tests::hex;
+
+    // see <https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38a.pdf>, F.5.
+    #[rstest]
+    #[case(
+        Encryption::Aes128Ctr,
+        "2b7e151628aed2a6abf7158809cf4f3c",
+        "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff"
+    )]
+    #[case(
+        Encryption::Aes192Ctr,
+        "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+        "1abc932417521ca24f2b0459fe7e6e0b090339ec0aa6faefd5ccc2c6f4ce8e94"
+    )]
+    #[case(
+        Encryption::Aes256Ctr,
+        "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+        "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5"
+    )]
+    fn it_matches_nist_vectors(
+        #[case] algorithm: Encryption,
+        #[case] key: &str,
+        #[case] ciphertext: &str,
+    ) {
+        let mut state = algorithm
+            .init(&hex(key), &hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff"))
+            .unwrap();
+
+        let mut buf = hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+
+        // Apply the keystream in two uneven parts, like the framing does
+        let (head, tail) = buf.split_at_mut(algorithm.block_size() - 4);
+        state.apply(head);
+        state.apply(tail);
+
+        assert_eq!(buf, hex(ciphertext));
+    }
+}
@@ -0,0 +1,223 @@
+#[cfg(feature = "hmac-sha2")]
+use hmac::Mac as _;
+
+use crate::Error;
+
+/// The _MAC algorithms_ implemented by this crate.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.4>.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hmac {
+    /// The `none` MAC algorithm, leaving the data unauthenticated.
+    #[default]
+    None,
+
+    /// The `hmac-sha2-256` MAC algorithm,
+    /// as defined in [RFC6668 section 2](https://datatracker.ietf.org/doc/html/rfc6668#section-2).
+    #[cfg(feature = "hmac-sha2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hmac-sha2")))]
+    HmacSha256,
+
+    /// The `hmac-sha2-512` MAC algorithm,
+    /// as defined in [RFC6668 section 2](https://datatracker.ietf.org/doc/html/rfc6668#section-2).
+    #[cfg(feature = "hmac-sha2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hmac-sha2")))]
+    HmacSha512,
+
+    /// The `hmac-sha2-256-etm@openssh.com` MAC algorithm,
+    /// computed over the encrypted packet.
+    #[cfg(feature = "hmac-sha2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hmac-sha2")))]
+    HmacSha256Etm,
+
+    /// The `hmac-sha2-512-etm@openssh.com` MAC algorithm,
+    /// computed over the encrypted packet.
+    #[cfg(feature = "hmac-sha2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hmac-sha2")))]
+    HmacSha512Etm,
+}
+
+impl Hmac {
+    /// All the [`Hmac`] algorithms, in order of preference.
+    pub const ALL: &'static [Self] = &[
+        #[cfg(feature = "hmac-sha2")]
+        Self::HmacSha512Etm,
+        #[cfg(feature = "hmac-sha2")]
+        Self::HmacSha256Etm,
+        #[cfg(feature = "hmac-sha2")]
+        Self::HmacSha512,
+        #[cfg(feature = "hmac-sha2")]
+        Self::HmacSha256,
+        Self::None,
+    ];
+
+    /// Get the [`Hmac`] algorithm's SSH identifier.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha256 => "hmac-sha2-256",
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha512 => "hmac-sha2-512",
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha256Etm => "hmac-sha2-256-etm@openssh.com",
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha512Etm => "hmac-sha2-512-etm@openssh.com",
+        }
+    }
+
+    /// Get the [`Hmac`] algorithm from it's SSH identifier, if implemented.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|algorithm| algorithm.name() == name)
+            .copied()
+    }
+
+    /// The size of the [`Hmac`] algorithm's key.
+    pub const fn key_size(&self) -> usize {
+        match self {
+            Self::None => 0,
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha256 | Self::HmacSha256Etm => 32,
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha512 | Self::HmacSha512Etm => 64,
+        }
+    }
+
+    pub(super) fn init(&self, key: &[u8]) -> Result<State, Error> {
+        if key.len() != self.key_size() {
+            return Err(Error::KeyLength(self.name()));
+        }
+
+        State::new(self, key)
+    }
+}
+
+impl crate::Mac for Hmac {
+    fn size(&self) -> usize {
+        match self {
+            Self::None => 0,
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha256 | Self::HmacSha256Etm => 32,
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha512 | Self::HmacSha512Etm => 64,
+        }
+    }
+
+    fn etm(&self) -> bool {
+        match self {
+            Self::None => false,
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha256 | Self::HmacSha512 => false,
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha256Etm | Self::HmacSha512Etm => true,
+        }
+    }
+}
+
+/// The keyed state of an [`Hmac`] algorithm.
+#[derive(Clone)]
+pub(super) enum State {
+    None,
+
+    #[cfg(feature = "hmac-sha2")]
+    HmacSha256(hmac::Hmac<sha2::Sha256>),
+
+    #[cfg(feature = "hmac-sha2")]
+    HmacSha512(hmac::Hmac<sha2::Sha512>),
+}
+
+impl State {
+    #[cfg_attr(not(feature = "hmac-sha2"), allow(unused_variables))]
+    fn new(algorithm: &Hmac, key: &[u8]) -> Result<Self, Error> {
+        Ok(match algorithm {
+            Hmac::None => Self::None,
+            #[cfg(feature = "hmac-sha2")]
+            Hmac::HmacSha256 | Hmac::HmacSha256Etm => Self::HmacSha256(
+                hmac::Hmac::new_from_slice(key).map_err(|_| Error::KeyLength(algorithm.name()))?,
+            ),
+            #[cfg(feature = "hmac-sha2")]
+            Hmac::HmacSha512 | Hmac::HmacSha512Etm => Self::HmacSha512(
+                hmac::Hmac::new_from_slice(key).map_err(|_| Error::KeyLength(algorithm.name()))?,
+            ),
+        })
+    }
+
+    /// Compute the _Message Authentication Code_ of the concatenation of the `parts` into `mac`,
+    /// which must be of the size of the algorithm's _Message Authentication Code_.
+    #[cfg_attr(not(feature = "hmac-sha2"), allow(unused_variables))]
+    pub(super) fn sign_into(&self, parts: &[&[u8]], mac: &mut [u8]) {
+        match self {
+            Self::None => (),
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha256(hmac) => {
+                let mut hmac = hmac.clone();
+                parts.iter().for_each(|part| hmac.update(part));
+
+                mac.copy_from_slice(&hmac.finalize().into_bytes());
+            }
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha512(hmac) => {
+                let mut hmac = hmac.clone();
+                parts.iter().for_each(|part| hmac.update(part));
+
+                mac.copy_from_slice(&hmac.finalize().into_bytes());
+            }
+        }
+    }
+
+    /// Verify in constant-time the _Message Authentication Code_ of the concatenation of the `parts`.
+    #[cfg_attr(not(feature = "hmac-sha2"), allow(unused_variables))]
+    pub(super) fn verify(&self, parts: &[&[u8]], mac: &[u8]) -> bool {
+        match self {
+            Self::None => mac.is_empty(),
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha256(hmac) => {
+                let mut hmac = hmac.clone();
+                parts.iter().for_each(|part| hmac.update(part));
+
+                hmac.verify_slice(mac).is_ok()
+            }
+            #[cfg(feature = "hmac-sha2")]
+            Self::HmacSha512(hmac) => {
+                let mut hmac = hmac.clone();
+                parts.iter().for_each(|part| hmac.update(part));
+
+                hmac.verify_slice(mac).is_ok()
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "hmac-sha2"))]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{crypto::cipher::tests::hex, Mac};
+
+    // see <https://datatracker.ietf.org/doc/html/rfc4231#section-4.3>.
+    #[rstest]
+    #[case(
+        Hmac::HmacSha256,
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    )]
+    #[case(
+        Hmac::HmacSha512,
+        "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+    )]
+    fn it_matches_rfc4231_vectors(#[case] algorithm: Hmac, #[case] mac: &str) {
+        let state = State::new(&algorithm, b"Jefe").unwrap();
+        let parts: &[&[u8]] = &[b"what do ya want ", b"for nothing?"];
+
+        let mut signed = vec![0; algorithm.size()];
+        state.sign_into(parts, &mut signed);
+
+        assert_eq!(signed, hex(mac));
+        assert!(state.verify(parts, &hex(mac)));
+        assert!(!state.verify(&parts[1..], &hex(mac)));
+    }
+}
@@ -0,0 +1,432 @@
+//! Reference implementations of the [`OpeningCipher`] and [`SealingCipher`] traits.
+
+use rand_core::RngCore;
+
+use crate::{
+    CipherCore, Error, Mac, OpeningCipher, SealingCipher, PACKET_MAX_SIZE, PACKET_MIN_SIZE,
+};
+
+mod encryption;
+pub use encryption::Encryption;
+
+mod mac;
+pub use mac::Hmac;
+
+mod compression;
+pub use compression::Compression;
+
+/// A keyed [`OpeningCipher`] and [`SealingCipher`] for a single direction of the transport,
+/// combining an [`Encryption`] algorithm, an [`Hmac`] algorithm and a [`Compression`] algorithm.
+pub struct Cipher {
+    encryption: Encryption,
+    encryption_state: encryption::State,
+
+    hmac: Hmac,
+    hmac_state: mac::State,
+
+    compression: Compression,
+    compression_state: compression::State,
+
+    max_packet_size: usize,
+    min_packet_size: usize,
+}
+
+impl Cipher {
+    /// Create a new [`Cipher`] from the [`Encryption`] algorithm with it's `key` and `iv`,
+    /// and the [`Hmac`] algorithm with it's `mac_key`.
+    pub fn new(
+        encryption: Encryption,
+        key: &[u8],
+        iv: &[u8],
+        hmac: Hmac,
+        mac_key: &[u8],
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            encryption,
+            encryption_state: encryption.init(key, iv)?,
+            hmac,
+            hmac_state: hmac.init(mac_key)?,
+            compression: Compression::None,
+            compression_state: compression::State::None,
+            max_packet_size: PACKET_MAX_SIZE,
+            min_packet_size: PACKET_MIN_SIZE,
+        })
+    }
+
+    /// Use the [`Compression`] algorithm for the payloads of this [`Cipher`].
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self.compression_state = compression.init();
+
+        self
+    }
+
+    /// Allow packets of up to `size` bytes, in place of the default [`PACKET_MAX_SIZE`],
+    /// which also bounds the size of the decompressed payloads.
+    pub fn with_max_packet_size(mut self, size: usize) -> Self {
+        self.max_packet_size = size;
+
+        self
+    }
+
+    /// Pad packets up to `size` bytes and reject smaller ones, in place of the default
+    /// [`PACKET_MIN_SIZE`], see [`CipherCore::min_packet_size`].
+    pub fn with_min_packet_size(mut self, size: usize) -> Self {
+        self.min_packet_size = size;
+
+        self
+    }
+
+    /// Activate the delayed [`Compression`] algorithms such as `zlib@openssh.com`, to be called
+    /// once the `SSH_MSG_USERAUTH_SUCCESS` message has been sent or received in this direction.
+    pub fn activate_delayed_compression(&mut self) {
+        self.compression_state.activate();
+    }
+
+    /// The [`Encryption`] algorithm of the [`Cipher`].
+    pub fn encryption(&self) -> Encryption {
+        self.encryption
+    }
+
+    /// The [`Compression`] algorithm of the [`Cipher`].
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+}
+
+impl Default for Cipher {
+    fn default() -> Self {
+        Self {
+            encryption: Encryption::None,
+            encryption_state: encryption::State::None,
+            hmac: Hmac::None,
+            hmac_state: mac::State::None,
+            compression: Compression::None,
+            compression_state: compression::State::None,
+            max_packet_size: PACKET_MAX_SIZE,
+            min_packet_size: PACKET_MIN_SIZE,
+        }
+    }
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cipher")
+            .field("encryption", &self.encryption)
+            .field("hmac", &self.hmac)
+            .field("compression", &self.compression)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CipherCore for Cipher {
+    type Err = Error;
+    type Mac = Hmac;
+
+    fn mac(&self) -> &Self::Mac {
+        &self.hmac
+    }
+
+    fn block_size(&self) -> usize {
+        self.encryption.block_size()
+    }
+
+    fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
+
+    fn min_packet_size(&self) -> usize {
+        self.min_packet_size
+    }
+}
+
+impl OpeningCipher for Cipher {
+    fn decrypt<B: AsMut<[u8]>>(&mut self, mut buf: B) -> Result<(), Self::Err> {
+        self.encryption_state.apply(buf.as_mut());
+
+        Ok(())
+    }
+
+    fn open<B: AsRef<[u8]>>(&mut self, buf: B, mac: Vec<u8>, seq: u32) -> Result<(), Self::Err> {
+        if self
+            .hmac_state
+            .verify(&[&seq.to_be_bytes(), buf.as_ref()], &mac)
+        {
+            Ok(())
+        } else {
+            Err(Error::Integrity)
+        }
+    }
+
+    fn decompress(&mut self, buf: Vec<u8>) -> Result<Vec<u8>, Self::Err> {
+        self.compression_state.decompress(buf, self.max_packet_size)
+    }
+}
+
+impl SealingCipher for Cipher {
+    fn compress<B: AsRef<[u8]>>(&mut self, buf: B) -> Result<Vec<u8>, Self::Err> {
+        self.compression_state.compress(buf.as_ref())
+    }
+
+    fn compress_into(&mut self, buf: &[u8], out: &mut Vec<u8>) -> Result<(), Self::Err> {
+        self.compression_state.compress_into(buf, out)
+    }
+
+    fn pad(&mut self, buf: Vec<u8>, padding: u8) -> Result<Vec<u8>, Self::Err> {
+        let mut padded = vec![0; std::mem::size_of_val(&padding) + buf.len() + padding as usize];
+
+        let (padlen, rest) = padded.split_at_mut(std::mem::size_of_val(&padding));
+        let (payload, padding_bytes) = rest.split_at_mut(buf.len());
+
+        padlen[0] = padding;
+        payload.copy_from_slice(&buf);
+        rand_core::OsRng.fill_bytes(padding_bytes);
+
+        Ok(padded)
+    }
+
+    fn fill_padding(&mut self, padding: &mut [u8]) -> Result<(), Self::Err> {
+        rand_core::OsRng.fill_bytes(padding);
+
+        Ok(())
+    }
+
+    fn encrypt<B: AsMut<[u8]>>(&mut self, mut buf: B) -> Result<(), Self::Err> {
+        self.encryption_state.apply(buf.as_mut());
+
+        Ok(())
+    }
+
+    fn seal<B: AsRef<[u8]>>(&mut self, buf: B, seq: u32) -> Result<Vec<u8>, Self::Err> {
+        let mut mac = vec![0; self.hmac.size()];
+        self.seal_into(buf.as_ref(), seq, &mut mac)?;
+
+        Ok(mac)
+    }
+
+    fn seal_into(&mut self, buf: &[u8], seq: u32, mac: &mut [u8]) -> Result<(), Self::Err> {
+        self.hmac_state.sign_into(&[&seq.to_be_bytes(), buf], mac);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::Packet;
+
+    #[cfg(any(feature = "aes-ctr", feature = "hmac-sha2"))]
+    pub(super) fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn pair(encryption: Encryption, hmac: Hmac) -> (Cipher, Cipher) {
+        let key = vec![0x42; encryption.key_size()];
+        let iv = vec![0x24; encryption.iv_size()];
+        let mac_key = vec![0x69; hmac.key_size()];
+
+        (
+            Cipher::new(encryption, &key, &iv, hmac, &mac_key).unwrap(),
+            Cipher::new(encryption, &key, &iv, hmac, &mac_key).unwrap(),
+        )
+    }
+
+    #[rstest]
+    #[case(Compression::None)]
+    #[cfg_attr(feature = "zlib", case(Compression::Zlib))]
+    #[cfg_attr(feature = "zlib", case(Compression::ZlibOpenssh))]
+    fn it_roundtrips_compressed(#[case] compression: Compression) {
+        let (sealing, opening) = pair(Encryption::ALL[0], Hmac::ALL[0]);
+        let (mut sealing, mut opening) = (
+            sealing.with_compression(compression),
+            opening.with_compression(compression),
+        );
+
+        let mut buf = Vec::new();
+        for seq in 0..3 {
+            if seq == 1 {
+                sealing.activate_delayed_compression();
+            }
+
+            Packet {
+                payload: vec![seq as u8; 1000 * seq as usize],
+            }
+            .to_sync_writer(&mut buf, &mut sealing, seq)
+            .unwrap();
+        }
+
+        let mut reader = &buf[..];
+        for seq in 0..3 {
+            if seq == 1 {
+                opening.activate_delayed_compression();
+            }
+
+            let packet = Packet::from_sync_reader(&mut reader, &mut opening, seq).unwrap();
+
+            assert_eq!(packet.payload, vec![seq as u8; 1000 * seq as usize]);
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn it_roundtrips() {
+        for (&encryption, &hmac) in Encryption::ALL
+            .iter()
+            .flat_map(|encryption| Hmac::ALL.iter().map(move |hmac| (encryption, hmac)))
+        {
+            let (mut sealing, mut opening) = pair(encryption, hmac);
+
+            let mut buf = Vec::new();
+            for seq in 0..3 {
+                Packet {
+                    payload: vec![seq as u8; 100 * seq as usize],
+                }
+                .to_sync_writer(&mut buf, &mut sealing, seq)
+                .unwrap();
+            }
+
+            let mut reader = &buf[..];
+            for seq in 0..3 {
+                let packet = Packet::from_sync_reader(&mut reader, &mut opening, seq).unwrap();
+
+                assert_eq!(packet.payload, vec![seq as u8; 100 * seq as usize]);
+            }
+            assert!(reader.is_empty());
+        }
+    }
+
+    #[cfg(feature = "hmac-sha2")]
+    #[rstest]
+    #[case(Hmac::HmacSha256)]
+    #[case(Hmac::HmacSha512Etm)]
+    fn it_rejects_tampered(#[case] hmac: Hmac) {
+        let (mut sealing, mut opening) = pair(Encryption::ALL[0], hmac);
+
+        let mut buf = Vec::new();
+        Packet {
+            payload: b"tampered".to_vec(),
+        }
+        .to_sync_writer(&mut buf, &mut sealing, 0)
+        .unwrap();
+
+        let last = buf.len() - hmac.size() - 1;
+        buf[last] ^= 0x01;
+
+        assert!(matches!(
+            Packet::from_sync_reader(&mut &buf[..], &mut opening, 0),
+            Err(Error::Integrity)
+        ));
+    }
+
+    #[test]
+    fn it_allows_max_packet_size() {
+        let payload = vec![0x42; 256 * 1024];
+        let (mut sealing, opening) = pair(Encryption::ALL[0], Hmac::ALL[0]);
+
+        let mut buf = Vec::new();
+        assert!(Packet {
+            payload: payload.clone()
+        }
+        .to_sync_writer(&mut buf, &mut sealing, 0)
+        .is_err());
+        assert!(buf.is_empty());
+
+        let (mut sealing, mut opening) = (
+            sealing.with_max_packet_size(300 * 1024),
+            opening.with_max_packet_size(300 * 1024),
+        );
+        Packet {
+            payload: payload.clone(),
+        }
+        .to_sync_writer(&mut buf, &mut sealing, 0)
+        .unwrap();
+
+        let packet = Packet::from_sync_reader(&mut &buf[..], &mut opening, 0).unwrap();
+        assert_eq!(packet.payload, payload);
+    }
+
+    #[test]
+    fn it_honors_min_packet_size() {
+        let (sealing, opening) = pair(Encryption::None, Hmac::None);
+        let (mut sealing, mut opening) = (
+            sealing.with_min_packet_size(64),
+            opening.with_min_packet_size(64),
+        );
+
+        let mut buf = Vec::new();
+        Packet { payload: vec![21] }
+            .to_sync_writer(&mut buf, &mut sealing, 0)
+            .unwrap();
+        assert_eq!(buf.len(), 64);
+
+        let packet = Packet::from_sync_reader(&mut &buf[..], &mut opening, 0).unwrap();
+        assert_eq!(packet.payload, [21]);
+
+        // Packets smaller than the minimum are rejected when opened
+        let (mut sealing, _) = pair(Encryption::None, Hmac::None);
+        let mut buf = Vec::new();
+        Packet { payload: vec![21] }
+            .to_sync_writer(&mut buf, &mut sealing, 0)
+            .unwrap();
+        assert!(Packet::from_sync_reader(&mut &buf[..], &mut opening, 1).is_err());
+
+        // The padding can't grow small packets past 255 bytes
+        let mut sealing = sealing.with_min_packet_size(1024);
+        let mut buf = Vec::new();
+        assert!(Packet { payload: vec![21] }
+            .to_sync_writer(&mut buf, &mut sealing, 1)
+            .is_err());
+        assert!(buf.is_empty());
+    }
+
+    #[rstest]
+    #[case(Encryption::None, 1, 0, Hmac::None, 0, "none")]
+    #[cfg_attr(
+        feature = "aes-ctr",
+        case(Encryption::Aes128Ctr, 32, 16, Hmac::None, 0, "aes128-ctr")
+    )]
+    #[cfg_attr(
+        feature = "aes-ctr",
+        case(Encryption::Aes128Ctr, 16, 12, Hmac::None, 0, "aes128-ctr")
+    )]
+    #[cfg_attr(
+        feature = "aes-ctr",
+        case(Encryption::Aes192Ctr, 16, 16, Hmac::None, 0, "aes192-ctr")
+    )]
+    #[cfg_attr(
+        feature = "aes-ctr",
+        case(Encryption::Aes256Ctr, 24, 16, Hmac::None, 0, "aes256-ctr")
+    )]
+    #[cfg_attr(
+        feature = "hmac-sha2",
+        case(Encryption::None, 0, 0, Hmac::HmacSha256, 16, "hmac-sha2-256")
+    )]
+    #[cfg_attr(
+        feature = "hmac-sha2",
+        case(Encryption::None, 0, 0, Hmac::HmacSha256, 64, "hmac-sha2-256")
+    )]
+    #[cfg_attr(
+        feature = "hmac-sha2",
+        case(Encryption::None, 0, 0, Hmac::HmacSha512, 32, "hmac-sha2-512")
+    )]
+    fn it_rejects_key_length(
+        #[case] encryption: Encryption,
+        #[case] key: usize,
+        #[case] iv: usize,
+        #[case] hmac: Hmac,
+        #[case] mac_key: usize,
+        #[case] algorithm: &str,
+    ) {
+        assert!(matches!(
+            Cipher::new(encryption, &vec![0; key], &vec![0; iv], hmac, &vec![0; mac_key]),
+            Err(Error::KeyLength(name)) if name == algorithm
+        ));
+    }
+}
@@ -3,7 +3,120 @@
 use binrw::binwrite;
 
 use super::Lengthed;
-use crate::{arch, trans};
+use crate::{arch, trans, Error};
+
+/// The exchange hash for DH, computed as the
+/// hash of the concatenation of the following.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-8>.
+#[binwrite]
+#[derive(Debug)]
+#[bw(big)]
+pub struct Dh<'b> {
+    /// Client's identification string (`\r` and `\n` excluded).
+    pub v_c: arch::Bytes<'b>,
+
+    /// Server's identification string (`\r` and `\n` excluded).
+    pub v_s: arch::Bytes<'b>,
+
+    /// Payload of the client's `SSH_MSG_KEXINIT` message.
+    pub i_c: Lengthed<&'b trans::KexInit<'b>>,
+
+    /// Payload of the server's `SSH_MSG_KEXINIT` message.
+    pub i_s: Lengthed<&'b trans::KexInit<'b>>,
+
+    /// Server's public host key.
+    pub k_s: arch::Bytes<'b>,
+
+    /// Exchange value sent by the client.
+    pub e: arch::MpInt<'b>,
+
+    /// Exchange value sent by the server.
+    pub f: arch::MpInt<'b>,
+
+    /// Computed shared secret.
+    pub k: arch::MpInt<'b>,
+}
+
+impl Dh<'_> {
+    /// Produce the exchange hash with the specified digest algorithm.
+    #[cfg(feature = "digest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+    pub fn hash<D: digest::Digest>(&self) -> digest::Output<D> {
+        use binrw::BinWrite;
+
+        let mut buffer = Vec::new();
+        self.write(&mut std::io::Cursor::new(&mut buffer))
+            .expect("The binrw structure serialization failed");
+
+        D::digest(&buffer)
+    }
+}
+
+/// The exchange hash for DH group exchange, computed as the
+/// hash of the concatenation of the following.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4419#section-3>.
+#[binwrite]
+#[derive(Debug)]
+#[bw(big)]
+pub struct DhGex<'b> {
+    /// Client's identification string (`\r` and `\n` excluded).
+    pub v_c: arch::Bytes<'b>,
+
+    /// Server's identification string (`\r` and `\n` excluded).
+    pub v_s: arch::Bytes<'b>,
+
+    /// Payload of the client's `SSH_MSG_KEXINIT` message.
+    pub i_c: Lengthed<&'b trans::KexInit<'b>>,
+
+    /// Payload of the server's `SSH_MSG_KEXINIT` message.
+    pub i_s: Lengthed<&'b trans::KexInit<'b>>,
+
+    /// Server's public host key.
+    pub k_s: arch::Bytes<'b>,
+
+    /// Minimal size in bits of an acceptable group,
+    /// omitted if `SSH_MSG_KEX_DH_GEX_REQUEST_OLD` was used.
+    pub min: Option<u32>,
+
+    /// Preferred size in bits of the group the server will send.
+    pub n: u32,
+
+    /// Maximal size in bits of an acceptable group,
+    /// omitted if `SSH_MSG_KEX_DH_GEX_REQUEST_OLD` was used.
+    pub max: Option<u32>,
+
+    /// Safe prime of the group.
+    pub p: arch::MpInt<'b>,
+
+    /// Generator for the subgroup.
+    pub g: arch::MpInt<'b>,
+
+    /// Exchange value sent by the client.
+    pub e: arch::MpInt<'b>,
+
+    /// Exchange value sent by the server.
+    pub f: arch::MpInt<'b>,
+
+    /// Computed shared secret.
+    pub k: arch::MpInt<'b>,
+}
+
+impl DhGex<'_> {
+    /// Produce the exchange hash with the specified digest algorithm.
+    #[cfg(feature = "digest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+    pub fn hash<D: digest::Digest>(&self) -> digest::Output<D> {
+        use binrw::BinWrite;
+
+        let mut buffer = Vec::new();
+        self.write(&mut std::io::Cursor::new(&mut buffer))
+            .expect("The binrw structure serialization failed");
+
+        D::digest(&buffer)
+    }
+}
 
 /// The exchange hash for ECDH, computed as the
 /// hash of the concatenation of the following.
@@ -34,7 +147,8 @@ pub struct Ecdh<'b> {
     /// Server's ephemeral public key octet string.
     pub q_s: arch::Bytes<'b>,
 
-    /// Computed shared secret.
+    /// Computed shared secret, see [`curve25519_secret`] and
+    /// [`curve448_secret`] for the Montgomery curves.
     pub k: arch::MpInt<'b>,
 }
 
@@ -52,3 +166,390 @@ impl Ecdh<'_> {
         D::digest(&buffer)
     }
 }
+
+/// The exchange hash for hybrid post-quantum key-exchanges, computed as the
+/// hash of the concatenation of the following.
+///
+/// This differs from [`Ecdh`] in that the shared secret `K` is encoded as a `string`,
+/// see `hybrid_secret` to compute it.
+///
+/// see <https://datatracker.ietf.org/doc/html/draft-ietf-sshm-mlkem-hybrid-kex#section-2.4>.
+#[binwrite]
+#[derive(Debug)]
+#[bw(big)]
+pub struct Hybrid<'b> {
+    /// Client's identification string (`\r` and `\n` excluded).
+    pub v_c: arch::Bytes<'b>,
+
+    /// Server's identification string (`\r` and `\n` excluded).
+    pub v_s: arch::Bytes<'b>,
+
+    /// Payload of the client's `SSH_MSG_KEXINIT` message.
+    pub i_c: Lengthed<&'b trans::KexInit<'b>>,
+
+    /// Payload of the server's `SSH_MSG_KEXINIT` message.
+    pub i_s: Lengthed<&'b trans::KexInit<'b>>,
+
+    /// Server's public host key.
+    pub k_s: arch::Bytes<'b>,
+
+    /// Client's hybrid public key octet string.
+    pub q_c: arch::Bytes<'b>,
+
+    /// Server's hybrid ciphertext octet string.
+    pub q_s: arch::Bytes<'b>,
+
+    /// Computed shared secret.
+    pub k: arch::Bytes<'b>,
+}
+
+impl Hybrid<'_> {
+    /// Produce the exchange hash with the specified digest algorithm.
+    #[cfg(feature = "digest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+    pub fn hash<D: digest::Digest>(&self) -> digest::Output<D> {
+        use binrw::BinWrite;
+
+        let mut buffer = Vec::new();
+        self.write(&mut std::io::Cursor::new(&mut buffer))
+            .expect("The binrw structure serialization failed");
+
+        D::digest(&buffer)
+    }
+}
+
+/// The hybrid post-quantum key-exchange methods, combining a
+/// post-quantum key encapsulation mechanism with X25519.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HybridMethod {
+    /// The `sntrup761x25519-sha512@openssh.com` key-exchange method.
+    Sntrup761X25519,
+
+    /// The `mlkem768x25519-sha256` key-exchange method.
+    Mlkem768X25519,
+}
+
+impl HybridMethod {
+    /// The size of the X25519 public keys, concatenated after the post-quantum part.
+    pub const X25519_SIZE: usize = 32;
+
+    /// Get the name of the [`HybridMethod`] as negotiated in `SSH_MSG_KEXINIT`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sntrup761X25519 => "sntrup761x25519-sha512@openssh.com",
+            Self::Mlkem768X25519 => "mlkem768x25519-sha256",
+        }
+    }
+
+    /// Get the [`HybridMethod`] from its negotiated `name`, if known.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sntrup761x25519-sha512@openssh.com" | "sntrup761x25519-sha512" => {
+                Some(Self::Sntrup761X25519)
+            }
+            "mlkem768x25519-sha256" => Some(Self::Mlkem768X25519),
+            _ => None,
+        }
+    }
+
+    /// The size of the post-quantum public key sent by the client in `q_c`.
+    pub fn public_key_size(&self) -> usize {
+        match self {
+            Self::Sntrup761X25519 => 1158,
+            Self::Mlkem768X25519 => 1184,
+        }
+    }
+
+    /// The size of the post-quantum ciphertext sent by the server in `q_s`.
+    pub fn ciphertext_size(&self) -> usize {
+        match self {
+            Self::Sntrup761X25519 => 1039,
+            Self::Mlkem768X25519 => 1088,
+        }
+    }
+
+    fn split<'b>(&self, blob: &'b [u8], size: usize) -> Result<HybridShare<'b>, Error> {
+        if blob.len() != size + Self::X25519_SIZE {
+            return Err(Error::KeyLength(self.name()));
+        }
+
+        let (pq, x25519) = blob.split_at(size);
+
+        Ok(HybridShare {
+            pq,
+            x25519: x25519
+                .try_into()
+                .map_err(|_| Error::KeyLength(self.name()))?,
+        })
+    }
+}
+
+/// One side's share of a hybrid post-quantum key-exchange, split
+/// into its post-quantum and X25519 parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HybridShare<'b> {
+    /// The post-quantum public key or ciphertext.
+    pub pq: &'b [u8],
+
+    /// The X25519 public key.
+    pub x25519: &'b [u8; 32],
+}
+
+impl HybridShare<'_> {
+    /// Concatenate both parts of the share into a single octet string.
+    pub fn join(&self) -> Vec<u8> {
+        [self.pq, self.x25519.as_slice()].concat()
+    }
+}
+
+impl trans::KexEcdhInit<'_> {
+    /// Split the client's `q_c` in its post-quantum public key and X25519 public key,
+    /// as sent for the hybrid key-exchange `method`.
+    pub fn hybrid(&self, method: HybridMethod) -> Result<HybridShare<'_>, Error> {
+        method.split(&self.q_c, method.public_key_size())
+    }
+
+    /// Create the message from the client's [`HybridShare`].
+    pub fn from_hybrid(share: &HybridShare<'_>) -> Self {
+        Self {
+            q_c: arch::Bytes::owned(share.join()),
+        }
+    }
+}
+
+impl trans::KexEcdhReply<'_> {
+    /// Split the server's `q_s` in its post-quantum ciphertext and X25519 public key,
+    /// as sent for the hybrid key-exchange `method`.
+    pub fn hybrid(&self, method: HybridMethod) -> Result<HybridShare<'_>, Error> {
+        method.split(&self.q_s, method.ciphertext_size())
+    }
+}
+
+/// Combine the post-quantum shared secret and the raw X25519 output
+/// into the shared secret `K` for the [`Hybrid`] exchange hash, with the digest
+/// algorithm of the method.
+///
+/// An all-zero X25519 output is rejected with [`Error::SharedSecret`].
+///
+/// see <https://datatracker.ietf.org/doc/html/draft-ietf-sshm-mlkem-hybrid-kex#section-2.4>.
+#[cfg(feature = "digest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+pub fn hybrid_secret<D: digest::Digest>(
+    pq: &[u8],
+    x25519: &[u8; 32],
+) -> Result<arch::Bytes<'static>, Error> {
+    if x25519.iter().fold(0, |acc, byte| acc | byte) == 0 {
+        return Err(Error::SharedSecret);
+    }
+
+    Ok(arch::Bytes::owned(
+        D::new()
+            .chain_update(pq)
+            .chain_update(x25519)
+            .finalize()
+            .to_vec(),
+    ))
+}
+
+/// Convert the raw output of the X25519 function into the shared secret `K`,
+/// to be provided to the [`Ecdh`] exchange hash for the `curve25519-sha256` method.
+///
+/// The output is interpreted as an unsigned integer in network byte order,
+/// and an all-zero output is rejected with [`Error::SharedSecret`].
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc8731#section-3.1>.
+pub fn curve25519_secret(output: &[u8; 32]) -> Result<arch::MpInt<'_>, Error> {
+    montgomery_secret(output)
+}
+
+/// Convert the raw output of the X448 function into the shared secret `K`,
+/// to be provided to the [`Ecdh`] exchange hash for the `curve448-sha512` method.
+///
+/// The output is interpreted as an unsigned integer in network byte order,
+/// and an all-zero output is rejected with [`Error::SharedSecret`].
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc8731#section-3.1>.
+pub fn curve448_secret(output: &[u8; 56]) -> Result<arch::MpInt<'_>, Error> {
+    montgomery_secret(output)
+}
+
+fn montgomery_secret(output: &[u8]) -> Result<arch::MpInt<'_>, Error> {
+    // Leading zeroes must be stripped from the `mpint` encoding, which is not constant-time,
+    // as the length of the hashed `mpint` depends on them anyway
+    let Some(start) = output.iter().position(|byte| *byte != 0) else {
+        return Err(Error::SharedSecret);
+    };
+
+    Ok(arch::MpInt::positive(&output[start..]))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    #[cfg(feature = "digest")]
+    use crate::packet::tests::kexinit;
+
+    /// Encode the `value` as a `string`, independently of the binrw structures.
+    #[cfg(feature = "digest")]
+    fn string(value: &[u8]) -> Vec<u8> {
+        [&(value.len() as u32).to_be_bytes(), value].concat()
+    }
+
+    #[cfg(feature = "digest")]
+    fn payload(kexinit: &trans::KexInit<'_>) -> Vec<u8> {
+        use binrw::BinWrite;
+
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        kexinit.write(&mut buffer).unwrap();
+
+        buffer.into_inner()
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn it_hashes_dh_exchanges() {
+        use sha2::Digest;
+
+        let client = kexinit(&["diffie-hellman-group14-sha256"], &[], &[], &[]);
+        let server = kexinit(&["diffie-hellman-group16-sha512"], &[], &[], &[]);
+
+        let hash = Dh {
+            v_c: b"SSH-2.0-client".to_vec().into(),
+            v_s: b"SSH-2.0-server".to_vec().into(),
+            i_c: (&client).into(),
+            i_s: (&server).into(),
+            k_s: b"host-key".to_vec().into(),
+            e: arch::MpInt::positive(&[0x80, 0x01]),
+            f: arch::MpInt::positive(&[0x7f, 0x02]),
+            k: arch::MpInt::positive(&[0x03; 32]),
+        }
+        .hash::<sha2::Sha256>();
+
+        // Exchange values with the high bit set are prefixed with a zero byte
+        let expected = sha2::Sha256::new()
+            .chain_update(string(b"SSH-2.0-client"))
+            .chain_update(string(b"SSH-2.0-server"))
+            .chain_update(string(&payload(&client)))
+            .chain_update(string(&payload(&server)))
+            .chain_update(string(b"host-key"))
+            .chain_update(string(&[0x00, 0x80, 0x01]))
+            .chain_update(string(&[0x7f, 0x02]))
+            .chain_update(string(&[0x03; 32]))
+            .finalize();
+        assert_eq!(hash, expected);
+    }
+
+    #[cfg(feature = "digest")]
+    #[rstest]
+    #[case(Some(2048), Some(8192), &[&2048u32.to_be_bytes()[..], &4096u32.to_be_bytes(), &8192u32.to_be_bytes()])]
+    #[case(None, None, &[&4096u32.to_be_bytes()[..]])]
+    fn it_hashes_dh_gex_exchanges(
+        #[case] min: Option<u32>,
+        #[case] max: Option<u32>,
+        #[case] sizes: &[&[u8]],
+    ) {
+        use sha2::Digest;
+
+        let kexinit = kexinit(&["diffie-hellman-group-exchange-sha256"], &[], &[], &[]);
+
+        let hash = DhGex {
+            v_c: b"SSH-2.0-client".to_vec().into(),
+            v_s: b"SSH-2.0-server".to_vec().into(),
+            i_c: (&kexinit).into(),
+            i_s: (&kexinit).into(),
+            k_s: b"host-key".to_vec().into(),
+            min,
+            n: 4096,
+            max,
+            p: arch::MpInt::positive(&[0xff; 16]),
+            g: arch::MpInt::positive(&[0x02]),
+            e: arch::MpInt::positive(&[0x01; 16]),
+            f: arch::MpInt::positive(&[0x02; 16]),
+            k: arch::MpInt::positive(&[0x03; 16]),
+        }
+        .hash::<sha2::Sha256>();
+
+        // The group sizes are bare `uint32`, `min` and `max` being absent for the old-style request
+        let expected = sha2::Sha256::new()
+            .chain_update(string(b"SSH-2.0-client"))
+            .chain_update(string(b"SSH-2.0-server"))
+            .chain_update(string(&payload(&kexinit)))
+            .chain_update(string(&payload(&kexinit)))
+            .chain_update(string(b"host-key"))
+            .chain_update(sizes.concat())
+            .chain_update(string(&[&[0x00][..], &[0xff; 16]].concat()))
+            .chain_update(string(&[0x02]))
+            .chain_update(string(&[0x01; 16]))
+            .chain_update(string(&[0x02; 16]))
+            .chain_update(string(&[0x03; 16]))
+            .finalize();
+        assert_eq!(hash, expected);
+    }
+
+    #[rstest]
+    #[case(&[0x01; 32], &[0x01; 32])]
+    #[case(&[0x00, 0x00, 0x7f], &[0x7f])]
+    #[case(&[0x00, 0x80, 0x01], &[0x00, 0x80, 0x01])]
+    #[case(&[0xff, 0x00], &[0x00, 0xff, 0x00])]
+    fn it_encodes_montgomery_secrets(#[case] output: &[u8], #[case] expected: &[u8]) {
+        assert_eq!(montgomery_secret(output).unwrap().as_ref(), expected);
+    }
+
+    #[test]
+    fn it_rejects_all_zero_secrets() {
+        assert!(matches!(
+            curve25519_secret(&[0; 32]),
+            Err(Error::SharedSecret)
+        ));
+        assert!(matches!(
+            curve448_secret(&[0; 56]),
+            Err(Error::SharedSecret)
+        ));
+    }
+
+    #[rstest]
+    #[case(HybridMethod::Sntrup761X25519)]
+    #[case(HybridMethod::Mlkem768X25519)]
+    fn it_splits_and_joins_hybrid_shares(#[case] method: HybridMethod) {
+        let pq = vec![0x42; method.public_key_size()];
+        let share = HybridShare {
+            pq: &pq,
+            x25519: &[0x24; 32],
+        };
+
+        let message = trans::KexEcdhInit::from_hybrid(&share);
+        assert_eq!(message.hybrid(method).unwrap(), share);
+
+        let message = trans::KexEcdhReply {
+            k_s: Default::default(),
+            q_s: message.q_c,
+            signature: Default::default(),
+        };
+        assert!(matches!(
+            message.hybrid(method),
+            Err(Error::KeyLength(name)) if name == method.name()
+        ));
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn it_computes_hybrid_secrets() {
+        use sha2::Digest;
+
+        let secret = hybrid_secret::<sha2::Sha256>(b"pq", &[0x01; 32]).unwrap();
+        let expected = sha2::Sha256::new()
+            .chain_update(b"pq")
+            .chain_update([0x01; 32])
+            .finalize();
+        assert_eq!(secret.as_ref(), expected.as_slice());
+
+        assert!(matches!(
+            hybrid_secret::<sha2::Sha256>(b"pq", &[0; 32]),
+            Err(Error::SharedSecret)
+        ));
+    }
+}
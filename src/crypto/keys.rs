@@ -0,0 +1,244 @@
+//! Facilities to derive the _session keys_ from the key-exchange.
+
+use binrw::BinWrite;
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+#[cfg(doc)]
+use crate::arch;
+
+/// The kind of key to be derived, identified by a letter in the derivation.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-7.2>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyKind {
+    /// Initial IV client to server (`A`).
+    IvClientToServer,
+
+    /// Initial IV server to client (`B`).
+    IvServerToClient,
+
+    /// Encryption key client to server (`C`).
+    KeyClientToServer,
+
+    /// Encryption key server to client (`D`).
+    KeyServerToClient,
+
+    /// Integrity key client to server (`E`).
+    IntegrityClientToServer,
+
+    /// Integrity key server to client (`F`).
+    IntegrityServerToClient,
+}
+
+impl KeyKind {
+    /// The letter identifying the [`KeyKind`] in the derivation.
+    pub const fn letter(&self) -> u8 {
+        match self {
+            Self::IvClientToServer => b'A',
+            Self::IvServerToClient => b'B',
+            Self::KeyClientToServer => b'C',
+            Self::KeyServerToClient => b'D',
+            Self::IntegrityClientToServer => b'E',
+            Self::IntegrityServerToClient => b'F',
+        }
+    }
+}
+
+/// The sizes of the keys to be derived for a single direction of the transport.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeySizes {
+    /// Size of the initial IV.
+    pub iv: usize,
+
+    /// Size of the encryption key.
+    pub key: usize,
+
+    /// Size of the integrity key.
+    pub integrity: usize,
+}
+
+/// The six _session keys_ derived from the shared secret `K`,
+/// the exchange hash `H` and the session identifier.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-7.2>.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "zeroize", derive(Zeroize))]
+pub struct Keys {
+    /// Initial IV client to server.
+    pub iv_client_to_server: Vec<u8>,
+
+    /// Initial IV server to client.
+    pub iv_server_to_client: Vec<u8>,
+
+    /// Encryption key client to server.
+    pub key_client_to_server: Vec<u8>,
+
+    /// Encryption key server to client.
+    pub key_server_to_client: Vec<u8>,
+
+    /// Integrity key client to server.
+    pub integrity_client_to_server: Vec<u8>,
+
+    /// Integrity key server to client.
+    pub integrity_server_to_client: Vec<u8>,
+}
+
+impl Keys {
+    /// Derive all the six keys with the specified digest algorithm, from the shared secret `k`,
+    /// the exchange hash `h` and the `session_id`, with the sizes for each direction.
+    ///
+    /// The shared secret `k` is encoded as it was in the exchange hash, either as an
+    /// [`arch::MpInt`] for most methods, or as [`arch::Bytes`] for the hybrid methods.
+    pub fn derive<D: digest::Digest>(
+        k: &impl for<'a> BinWrite<Args<'a> = ()>,
+        h: &[u8],
+        session_id: &[u8],
+        client_to_server: KeySizes,
+        server_to_client: KeySizes,
+    ) -> Self {
+        let derive = |kind, size| derive::<D>(k, h, session_id, kind, size);
+
+        Self {
+            iv_client_to_server: derive(KeyKind::IvClientToServer, client_to_server.iv),
+            iv_server_to_client: derive(KeyKind::IvServerToClient, server_to_client.iv),
+            key_client_to_server: derive(KeyKind::KeyClientToServer, client_to_server.key),
+            key_server_to_client: derive(KeyKind::KeyServerToClient, server_to_client.key),
+            integrity_client_to_server: derive(
+                KeyKind::IntegrityClientToServer,
+                client_to_server.integrity,
+            ),
+            integrity_server_to_client: derive(
+                KeyKind::IntegrityServerToClient,
+                server_to_client.integrity,
+            ),
+        }
+    }
+}
+
+/// Derive a single key of the specified `kind` and `size` with the specified digest algorithm,
+/// from the shared secret `k`, the exchange hash `h` and the `session_id`, see [`Keys::derive`].
+///
+/// When the requested `size` is longer than the digest's output, the key is
+/// extended by hashing the concatenation of `k`, `h` and the key so far.
+pub fn derive<D: digest::Digest>(
+    k: &impl for<'a> BinWrite<Args<'a> = ()>,
+    h: &[u8],
+    session_id: &[u8],
+    kind: KeyKind,
+    size: usize,
+) -> Vec<u8> {
+    let mut secret = Vec::new();
+    k.write_be(&mut std::io::Cursor::new(&mut secret))
+        .expect("The binrw structure serialization failed");
+
+    let mut key = D::new()
+        .chain_update(&secret)
+        .chain_update(h)
+        .chain_update([kind.letter()])
+        .chain_update(session_id)
+        .finalize()
+        .to_vec();
+
+    while key.len() < size {
+        let extension = D::new()
+            .chain_update(&secret)
+            .chain_update(h)
+            .chain_update(&key)
+            .finalize();
+
+        key.extend_from_slice(&extension);
+    }
+    key.truncate(size);
+
+    #[cfg(feature = "zeroize")]
+    secret.zeroize();
+
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::{
+        arch,
+        crypto::exchange::{hybrid_secret, Hybrid},
+        packet::tests::kexinit,
+    };
+
+    #[test]
+    fn it_derives_extended_keys() {
+        let k = arch::MpInt::positive(&[0x80, 0x01]);
+        let key = derive::<Sha256>(&k, &[0xaa; 32], &[0xbb; 32], KeyKind::KeyClientToServer, 48);
+
+        assert_eq!(
+            key,
+            [
+                0x8c, 0xb2, 0xc5, 0x08, 0xae, 0x33, 0xca, 0xa1, 0x7b, 0x1d, 0x58, 0x53, 0xb4, 0x08,
+                0x42, 0xa3, 0x1d, 0xd2, 0xfe, 0x8f, 0x3c, 0xe4, 0x0a, 0xd2, 0x82, 0x33, 0x27, 0xd7,
+                0x90, 0x7d, 0x1a, 0x52, 0x54, 0xf4, 0x43, 0x83, 0x87, 0x12, 0xeb, 0xaf, 0x4a, 0xc4,
+                0xd4, 0x9a, 0x8a, 0x0a, 0x1d, 0x1e,
+            ]
+        );
+
+        let keys = Keys::derive::<Sha256>(
+            &k,
+            &[0xaa; 32],
+            &[0xbb; 32],
+            KeySizes {
+                iv: 16,
+                key: 64,
+                integrity: 0,
+            },
+            Default::default(),
+        );
+        assert_eq!(keys.key_client_to_server[..48], key);
+        assert_eq!(keys.iv_client_to_server.len(), 16);
+        assert!(keys.integrity_client_to_server.is_empty());
+    }
+
+    #[test]
+    fn it_derives_hybrid_keys() {
+        let kexinit = kexinit(&["mlkem768x25519-sha256"], &[], &[], &[]);
+        let k = hybrid_secret::<Sha256>(&[0x42; 32], &[0x24; 32]).unwrap();
+
+        let h = Hybrid {
+            v_c: b"SSH-2.0-client".to_vec().into(),
+            v_s: b"SSH-2.0-server".to_vec().into(),
+            i_c: (&kexinit).into(),
+            i_s: (&kexinit).into(),
+            k_s: Default::default(),
+            q_c: vec![0x01; 1216].into(),
+            q_s: vec![0x02; 1120].into(),
+            k: k.clone(),
+        }
+        .hash::<Sha256>();
+
+        let keys = Keys::derive::<Sha256>(
+            &k,
+            &h,
+            &h,
+            KeySizes {
+                iv: 16,
+                key: 32,
+                integrity: 32,
+            },
+            Default::default(),
+        );
+
+        // The shared secret is encoded as a `string`, and not as an `mpint`
+        let expected = Sha256::new()
+            .chain_update(32u32.to_be_bytes())
+            .chain_update(&*k)
+            .chain_update(h)
+            .chain_update(b"C")
+            .chain_update(h)
+            .finalize();
+        assert_eq!(keys.key_client_to_server, expected.as_slice());
+        assert_eq!(keys.iv_client_to_server.len(), 16);
+    }
+}
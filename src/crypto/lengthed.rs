@@ -1,4 +1,7 @@
-use std::{io, ops::Deref};
+use std::{
+    io::{self, Read},
+    ops::Deref,
+};
 
 use binrw::{
     meta::{ReadEndian, WriteEndian},
@@ -35,10 +38,13 @@ where
         args: Self::Args<'_>,
     ) -> binrw::BinResult<Self> {
         let size = u32::read_be(reader)?;
-        let len = (size as usize).min(crate::PACKET_MAX_SIZE);
 
-        let mut buf = Vec::with_capacity(len);
-        reader.read_exact(&mut buf[..len])?;
+        // Avoid pre-allocating the untrusted `size`, and fail if the reader is exhausted early
+        let mut buf = Vec::new();
+        reader.by_ref().take(size.into()).read_to_end(&mut buf)?;
+        if buf.len() != size as usize {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        }
 
         T::read_options(&mut io::Cursor::new(&buf), endian, args).map(Self)
     }
@@ -63,12 +69,14 @@ where
         endian: binrw::Endian,
         args: Self::Args<'_>,
     ) -> binrw::BinResult<()> {
-        let mut buf = Vec::with_capacity(crate::PACKET_MAX_SIZE);
+        let mut buf = Vec::new();
         self.0
             .write_options(&mut io::Cursor::new(&mut buf), endian, args)?;
 
-        let len = buf.len();
-        let size: u32 = len.min(crate::PACKET_MAX_SIZE) as u32;
+        let size = u32::try_from(buf.len()).map_err(|_| binrw::Error::Custom {
+            pos: writer.stream_position().unwrap_or_default(),
+            err: Box::new(format!("Lengthed size too large, {}", buf.len())),
+        })?;
 
         size.write_be(writer)?;
         Ok(writer.write_all(&buf)?)
@@ -81,3 +89,36 @@ where
 {
     const ENDIAN: binrw::meta::EndianKind = T::ENDIAN;
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use binrw::{BinReaderExt, BinWriterExt};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::arch;
+
+    #[rstest]
+    #[case(vec![])]
+    #[case(vec![0x42; 256 * 1024])]
+    fn it_roundtrips(#[case] data: Vec<u8>) {
+        let mut buf = io::Cursor::new(Vec::new());
+        buf.write_be(&Lengthed(arch::Bytes::owned(data.clone())))
+            .unwrap();
+
+        buf.set_position(0);
+        let lengthed: Lengthed<arch::Bytes> = buf.read_be().unwrap();
+
+        assert_eq!(lengthed.as_ref(), &data[..]);
+    }
+
+    #[test]
+    fn it_rejects_truncated() {
+        let buf = [&1024u32.to_be_bytes()[..], &[0; 16]].concat();
+
+        assert!(io::Cursor::new(buf)
+            .read_be::<Lengthed<arch::Bytes>>()
+            .is_err());
+    }
+}
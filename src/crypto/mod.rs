@@ -1,7 +1,18 @@
-//! Collection of _exchange hashes_ and _signatures_ present in the protocol.
+//! Collection of _exchange hashes_, _signatures_ and _ciphers_ present in the protocol.
 
 mod lengthed;
 pub use lengthed::Lengthed;
 
 pub mod exchange;
 pub mod signature;
+
+#[cfg(feature = "digest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+pub mod keys;
+
+#[cfg(any(feature = "aes-ctr", feature = "hmac-sha2", feature = "zlib"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "aes-ctr", feature = "hmac-sha2", feature = "zlib")))
+)]
+pub mod cipher;
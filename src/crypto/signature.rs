@@ -68,3 +68,41 @@ impl Publickey<'_> {
         K::sign(key, &buffer)
     }
 }
+
+/// The data over which the _Message Integrity Code_ is computed with the GSS-API security
+/// context in the `gssapi-with-mic` authentication method, computed from the concatenation of the following.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-3.5>.
+#[binwrite]
+#[derive(Debug)]
+#[bw(big)]
+pub struct GssapiWithMic<'b> {
+    /// The session identifier issued by the key-exchange.
+    pub session_id: arch::Bytes<'b>,
+
+    #[bw(calc = 50)]
+    magic: u8,
+
+    /// Username for the auth request.
+    pub username: arch::Utf8<'b>,
+
+    /// Service name to query.
+    pub service_name: arch::Ascii<'b>,
+
+    #[bw(calc = "gssapi-with-mic".into())]
+    method: arch::Utf8<'b>,
+}
+
+impl GssapiWithMic<'_> {
+    /// Encode the structure, as the message to be passed to the `GSS_GetMIC()` and
+    /// `GSS_VerifyMIC()` calls of the established GSS-API security context.
+    pub fn encode(&self) -> Vec<u8> {
+        use binrw::BinWrite;
+
+        let mut buffer = Vec::new();
+        self.write(&mut std::io::Cursor::new(&mut buffer))
+            .expect("The binrw structure serialization failed");
+
+        buffer
+    }
+}
@@ -15,4 +15,36 @@ pub enum Error {
     /// An EOF occured while parsing.
     #[error("Unexpected EOF while waiting for SSH identifer")]
     UnexpectedEof,
+
+    /// An error occured while parsing or serializing binary data.
+    #[error(transparent)]
+    Binary(#[from] binrw::Error),
+
+    /// The _Message Authentication Code_ of the received packet didn't match.
+    #[error("The packet's integrity check failed")]
+    Integrity,
+
+    /// The provided key or initialization vector was not of the expected size.
+    #[error("The provided key material was not of the expected size for `{0}`")]
+    KeyLength(&'static str),
+
+    /// The computed shared secret was rejected, as mandated by the key-exchange method.
+    #[error("The computed shared secret was invalid")]
+    SharedSecret,
+
+    /// The peer violated the strict key-exchange rules.
+    #[error("Strict key-exchange violation, {0}")]
+    StrictKex(&'static str),
+
+    /// No common algorithm could be negotiated for an algorithm slot of the `SSH_MSG_KEXINIT` message.
+    #[error("No common algorithm could be negotiated for the {0}")]
+    Negotiation(crate::trans::Slot),
+
+    /// The algorithm name didn't conform to the naming rules of the protocol.
+    #[error("The algorithm name `{0}` is not conformant")]
+    AlgorithmName(String),
+
+    /// The payload compression or decompression failed.
+    #[error("The payload (de)compression failed, {0}")]
+    Compression(&'static str),
 }
@@ -30,6 +30,31 @@ impl Id {
         }
     }
 
+    /// Read an [`Id`], discarding any _extra lines_ sent by the server
+    /// from the provided synchronous `reader`.
+    pub fn from_sync_reader<R>(reader: &mut R) -> Result<Self, Error>
+    where
+        R: std::io::BufRead,
+    {
+        let text = std::io::BufRead::lines(reader)
+            // Skip extra lines the server can send before identifying
+            .find(|line| line.as_ref().map_or(true, |line| line.starts_with("SSH")))
+            .ok_or(Error::UnexpectedEof)??;
+
+        text.parse()
+    }
+
+    /// Write the [`Id`] to the provided synchronous `writer`.
+    pub fn to_sync_writer<W>(&self, writer: &mut W) -> Result<(), Error>
+    where
+        W: std::io::Write,
+    {
+        writer.write_all(self.to_string().as_bytes())?;
+        writer.write_all(b"\r\n")?;
+
+        Ok(())
+    }
+
     #[cfg(feature = "futures")]
     #[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
     /// Read an [`Id`], discarding any _extra lines_ sent by the server
@@ -64,6 +89,43 @@ impl Id {
 
         Ok(())
     }
+
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Read an [`Id`], discarding any _extra lines_ sent by the server
+    /// from the provided asynchronous `tokio` `reader`.
+    pub async fn from_tokio_reader<R>(reader: &mut R) -> Result<Self, Error>
+    where
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        let mut lines = tokio::io::AsyncBufReadExt::lines(reader);
+
+        let text = loop {
+            match lines.next_line().await? {
+                // Skip extra lines the server can send before identifying
+                Some(line) if !line.starts_with("SSH") => continue,
+                Some(line) => break line,
+                None => return Err(Error::UnexpectedEof),
+            }
+        };
+
+        text.parse()
+    }
+
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Write the [`Id`] to the provided asynchronous `tokio` `writer`.
+    pub async fn to_tokio_writer<W>(&self, writer: &mut W) -> Result<(), Error>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        writer.write_all(self.to_string().as_bytes()).await?;
+        writer.write_all(b"\r\n").await?;
+
+        Ok(())
+    }
 }
 
 impl std::fmt::Display for Id {
@@ -147,4 +209,13 @@ mod tests {
     fn it_reparses_consistently(#[case] id: Id) {
         assert_eq!(id, id.to_string().parse().unwrap());
     }
+
+    #[rstest]
+    #[case(b"SSH-2.0-billsSSH_3.6.3q3\r\n", Ok(Id::v2("billsSSH_3.6.3q3", None::<String>)))]
+    #[case(b"extra line\r\nSSH-2.0-billsSSH_3.6.3q3\r\n", Ok(Id::v2("billsSSH_3.6.3q3", None::<String>)))]
+    #[case(b"extra line\r\n", Err(Error::UnexpectedEof))]
+    #[case(b"SSH-2.0-\r\n", Err(Error::BadIdentifer(Default::default())))]
+    fn it_reads_from_sync_reader(#[case] text: &[u8], #[case] expected: Result<Id, Error>) {
+        assert_eq!(Id::from_sync_reader(&mut &text[..]), expected);
+    }
 }
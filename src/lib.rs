@@ -19,13 +19,26 @@ pub use error::Error;
 
 mod packet;
 pub use packet::{
-    CipherCore, IntoPacket, Mac, OpeningCipher, Packet, SealingCipher, PACKET_MAX_SIZE,
-    PACKET_MIN_SIZE,
+    BorrowRead, CipherCore, IntoPacket, Mac, OpeningCipher, Packet, PacketDecoder, PacketEncoder,
+    SealingCipher, PACKET_MAX_SIZE, PACKET_MIN_SIZE,
 };
 
+#[cfg(feature = "futures")]
+#[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+pub use packet::Framed;
+
 mod id;
 pub use id::Id;
 
+mod strict;
+pub use strict::StrictKex;
+
+mod rekey;
+pub use rekey::Rekey;
+
+mod message;
+pub use message::{KexFamily, Message, MessageContext};
+
 pub mod arch;
 pub mod connect;
 pub mod crypto;
@@ -0,0 +1,757 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use binrw::{
+    meta::{EndianKind, ReadEndian, WriteEndian},
+    BinRead, BinResult, BinWrite, Endian,
+};
+
+use crate::{arch, connect, trans, userauth};
+
+#[cfg(doc)]
+use crate::{IntoPacket, Packet};
+
+/// The context in which a [`Message`] is decoded, used to pick
+/// the right interpretation of message numbers shared by several messages.
+#[derive(Debug, Default, Clone)]
+pub struct MessageContext {
+    /// The family of the negotiated key-exchange method,
+    /// disambiguating the message numbers `30` to `34`.
+    pub kex: Option<KexFamily>,
+
+    /// The last authentication method sent in a `SSH_MSG_USERAUTH_REQUEST`,
+    /// as obtained from [`userauth::Method::as_ascii`], disambiguating the message numbers `60` and `61`.
+    pub userauth: Option<arch::Ascii<'static>>,
+}
+
+/// The family of a key-exchange method, which determines
+/// the messages used during the key-exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KexFamily {
+    /// Classic Diffie-Hellman key-exchange (`SSH_MSG_KEXDH_*`),
+    /// as defined in [RFC4253 section 8](https://datatracker.ietf.org/doc/html/rfc4253#section-8).
+    Dh,
+
+    /// Elliptic-curve Diffie-Hellman key-exchange (`SSH_MSG_KEX_ECDH_*`),
+    /// as defined in [RFC5656 section 4](https://datatracker.ietf.org/doc/html/rfc5656#section-4).
+    Ecdh,
+
+    /// Diffie-Hellman group exchange (`SSH_MSG_KEX_DH_GEX_*`),
+    /// as defined in [RFC4419 section 3](https://datatracker.ietf.org/doc/html/rfc4419#section-3).
+    Gex,
+
+    /// GSS-API authenticated key-exchange (`SSH_MSG_KEXGSS_*`),
+    /// as defined in [RFC4462 section 2](https://datatracker.ietf.org/doc/html/rfc4462#section-2).
+    Gss,
+}
+
+impl KexFamily {
+    /// Get the [`KexFamily`] of the provided key-exchange method `name`, if known.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "diffie-hellman-group1-sha1"
+            | "diffie-hellman-group14-sha1"
+            | "diffie-hellman-group14-sha256"
+            | "diffie-hellman-group15-sha512"
+            | "diffie-hellman-group16-sha512"
+            | "diffie-hellman-group17-sha512"
+            | "diffie-hellman-group18-sha512" => Some(Self::Dh),
+            "diffie-hellman-group-exchange-sha1" | "diffie-hellman-group-exchange-sha256" => {
+                Some(Self::Gex)
+            }
+            "curve25519-sha256" | "curve25519-sha256@libssh.org" | "curve448-sha512" => {
+                Some(Self::Ecdh)
+            }
+            // The hybrid post-quantum methods reuse the `SSH_MSG_KEX_ECDH_*` messages
+            "sntrup761x25519-sha512@openssh.com"
+            | "sntrup761x25519-sha512"
+            | "mlkem768x25519-sha256" => Some(Self::Ecdh),
+            name if name.starts_with("ecdh-sha2-") => Some(Self::Ecdh),
+            // The GSS-API methods are suffixed with the hash of the mechanism's OID
+            name if name.starts_with("gss-") => Some(Self::Gss),
+            _ => None,
+        }
+    }
+}
+
+/// Any of the messages of the SSH protocol known to this crate,
+/// decoded by dispatching on the _message number_.
+///
+/// The message numbers shared by several messages are
+/// disambiguated with the help of a [`MessageContext`].
+///
+/// Use [`Packet::message`] to decode a [`Packet`] into a [`Message`],
+/// and [`IntoPacket`] to encode it back.
+#[derive(Debug, Clone)]
+pub enum Message<'b> {
+    /// The `SSH_MSG_DISCONNECT` message.
+    Disconnect(trans::Disconnect<'b>),
+
+    /// The `SSH_MSG_IGNORE` message.
+    Ignore(trans::Ignore<'b>),
+
+    /// The `SSH_MSG_UNIMPLEMENTED` message.
+    Unimplemented(trans::Unimplemented),
+
+    /// The `SSH_MSG_DEBUG` message.
+    Debug(trans::Debug<'b>),
+
+    /// The `SSH_MSG_SERVICE_REQUEST` message.
+    ServiceRequest(trans::ServiceRequest<'b>),
+
+    /// The `SSH_MSG_SERVICE_ACCEPT` message.
+    ServiceAccept(trans::ServiceAccept<'b>),
+
+    /// The `SSH_MSG_EXT_INFO` message.
+    ExtInfo(trans::ExtInfo),
+
+    /// The `SSH_MSG_KEXINIT` message.
+    KexInit(trans::KexInit<'b>),
+
+    /// The `SSH_MSG_NEWKEYS` message.
+    NewKeys(trans::NewKeys),
+
+    /// The `SSH_MSG_KEXDH_INIT` message.
+    KexdhInit(trans::KexdhInit<'b>),
+
+    /// The `SSH_MSG_KEXDH_REPLY` message.
+    KexdhReply(trans::KexdhReply<'b>),
+
+    /// The `SSH_MSG_KEX_ECDH_INIT` message.
+    KexEcdhInit(trans::KexEcdhInit<'b>),
+
+    /// The `SSH_MSG_KEX_ECDH_REPLY` message.
+    KexEcdhReply(trans::KexEcdhReply<'b>),
+
+    /// The `SSH_MSG_KEX_DH_GEX_REQUEST_OLD` message.
+    KexDhGexRequestOld(trans::KexDhGexRequestOld),
+
+    /// The `SSH_MSG_KEX_DH_GEX_GROUP` message.
+    KexDhGexGroup(trans::KexDhGexGroup<'b>),
+
+    /// The `SSH_MSG_KEX_DH_GEX_INIT` message.
+    KexDhGexInit(trans::KexDhGexInit<'b>),
+
+    /// The `SSH_MSG_KEX_DH_GEX_REPLY` message.
+    KexDhGexReply(trans::KexDhGexReply<'b>),
+
+    /// The `SSH_MSG_KEX_DH_GEX_REQUEST` message.
+    KexDhGexRequest(trans::KexDhGexRequest),
+
+    /// The `SSH_MSG_KEXGSS_INIT` message.
+    KexgssInit(trans::KexgssInit<'b>),
+
+    /// The `SSH_MSG_KEXGSS_CONTINUE` message.
+    KexgssContinue(trans::KexgssContinue<'b>),
+
+    /// The `SSH_MSG_KEXGSS_COMPLETE` message.
+    KexgssComplete(trans::KexgssComplete<'b>),
+
+    /// The `SSH_MSG_KEXGSS_HOSTKEY` message.
+    KexgssHostkey(trans::KexgssHostkey<'b>),
+
+    /// The `SSH_MSG_KEXGSS_ERROR` message.
+    KexgssError(trans::KexgssError<'b>),
+
+    /// The `SSH_MSG_KEXGSS_GROUPREQ` message.
+    KexgssGroupreq(trans::KexgssGroupreq),
+
+    /// The `SSH_MSG_KEXGSS_GROUP` message.
+    KexgssGroup(trans::KexgssGroup<'b>),
+
+    /// The `SSH_MSG_USERAUTH_REQUEST` message.
+    UserauthRequest(userauth::Request<'b>),
+
+    /// The `SSH_MSG_USERAUTH_FAILURE` message.
+    UserauthFailure(userauth::Failure<'b>),
+
+    /// The `SSH_MSG_USERAUTH_SUCCESS` message.
+    UserauthSuccess(userauth::Success),
+
+    /// The `SSH_MSG_USERAUTH_BANNER` message.
+    UserauthBanner(userauth::Banner<'b>),
+
+    /// The `SSH_MSG_USERAUTH_PK_OK` message.
+    UserauthPkOk(userauth::PkOk<'b>),
+
+    /// The `SSH_MSG_USERAUTH_PASSWD_CHANGEREQ` message.
+    UserauthPasswdChangereq(userauth::PasswdChangereq<'b>),
+
+    /// The `SSH_MSG_USERAUTH_INFO_REQUEST` message.
+    UserauthInfoRequest(userauth::InfoRequest<'b>),
+
+    /// The `SSH_MSG_USERAUTH_INFO_RESPONSE` message.
+    UserauthInfoResponse(userauth::InfoResponse),
+
+    /// The `SSH_MSG_USERAUTH_GSSAPI_RESPONSE` message.
+    UserauthGssapiResponse(userauth::GssapiResponse<'b>),
+
+    /// The `SSH_MSG_USERAUTH_GSSAPI_TOKEN` message.
+    UserauthGssapiToken(userauth::GssapiToken<'b>),
+
+    /// The `SSH_MSG_USERAUTH_GSSAPI_EXCHANGE_COMPLETE` message.
+    UserauthGssapiExchangeComplete(userauth::GssapiExchangeComplete),
+
+    /// The `SSH_MSG_USERAUTH_GSSAPI_ERROR` message.
+    UserauthGssapiError(userauth::GssapiError<'b>),
+
+    /// The `SSH_MSG_USERAUTH_GSSAPI_ERRTOK` message.
+    UserauthGssapiErrtok(userauth::GssapiErrtok<'b>),
+
+    /// The `SSH_MSG_USERAUTH_GSSAPI_MIC` message.
+    UserauthGssapiMic(userauth::GssapiMic<'b>),
+
+    /// The `SSH_MSG_GLOBAL_REQUEST` message.
+    GlobalRequest(connect::GlobalRequest<'b>),
+
+    /// The `SSH_MSG_REQUEST_SUCCESS` message (empty body).
+    RequestSuccess(connect::RequestSuccess),
+
+    /// The `SSH_MSG_REQUEST_SUCCESS` message, with a bound port.
+    ForwardingSuccess(connect::ForwardingSuccess),
+
+    /// The `SSH_MSG_REQUEST_FAILURE` message.
+    RequestFailure(connect::RequestFailure),
+
+    /// The `SSH_MSG_CHANNEL_OPEN` message.
+    ChannelOpen(connect::ChannelOpen<'b>),
+
+    /// The `SSH_MSG_CHANNEL_OPEN_CONFIRMATION` message.
+    ChannelOpenConfirmation(connect::ChannelOpenConfirmation),
+
+    /// The `SSH_MSG_CHANNEL_OPEN_FAILURE` message.
+    ChannelOpenFailure(connect::ChannelOpenFailure<'b>),
+
+    /// The `SSH_MSG_CHANNEL_WINDOW_ADJUST` message.
+    ChannelWindowAdjust(connect::ChannelWindowAdjust),
+
+    /// The `SSH_MSG_CHANNEL_DATA` message.
+    ChannelData(connect::ChannelData<'b>),
+
+    /// The `SSH_MSG_CHANNEL_EXTENDED_DATA` message.
+    ChannelExtendedData(connect::ChannelExtendedData<'b>),
+
+    /// The `SSH_MSG_CHANNEL_EOF` message.
+    ChannelEof(connect::ChannelEof),
+
+    /// The `SSH_MSG_CHANNEL_CLOSE` message.
+    ChannelClose(connect::ChannelClose),
+
+    /// The `SSH_MSG_CHANNEL_REQUEST` message.
+    ChannelRequest(connect::ChannelRequest<'b>),
+
+    /// The `SSH_MSG_CHANNEL_SUCCESS` message.
+    ChannelSuccess(connect::ChannelSuccess),
+
+    /// The `SSH_MSG_CHANNEL_FAILURE` message.
+    ChannelFailure(connect::ChannelFailure),
+
+    /// The `SSH2_MSG_PING` message.
+    Ping(trans::Ping<'b>),
+
+    /// The `SSH2_MSG_PONG` message.
+    Pong(trans::Pong<'b>),
+
+    /// Any other message, unknown to this crate, which should
+    /// be answered with a [`trans::Unimplemented`] message.
+    Unknown {
+        /// The message number.
+        number: u8,
+
+        /// The rest of the message, excluding the `number`.
+        payload: Vec<u8>,
+    },
+}
+
+macro_rules! from {
+    ($($variant:ident($ty:ty)),+ $(,)?) => {
+        $(
+            impl<'b> From<$ty> for Message<'b> {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value)
+                }
+            }
+        )+
+    };
+}
+
+from! {
+    Disconnect(trans::Disconnect<'b>),
+    Ignore(trans::Ignore<'b>),
+    Unimplemented(trans::Unimplemented),
+    Debug(trans::Debug<'b>),
+    ServiceRequest(trans::ServiceRequest<'b>),
+    ServiceAccept(trans::ServiceAccept<'b>),
+    ExtInfo(trans::ExtInfo),
+    KexInit(trans::KexInit<'b>),
+    NewKeys(trans::NewKeys),
+    KexdhInit(trans::KexdhInit<'b>),
+    KexdhReply(trans::KexdhReply<'b>),
+    KexEcdhInit(trans::KexEcdhInit<'b>),
+    KexEcdhReply(trans::KexEcdhReply<'b>),
+    KexDhGexRequestOld(trans::KexDhGexRequestOld),
+    KexDhGexGroup(trans::KexDhGexGroup<'b>),
+    KexDhGexInit(trans::KexDhGexInit<'b>),
+    KexDhGexReply(trans::KexDhGexReply<'b>),
+    KexDhGexRequest(trans::KexDhGexRequest),
+    KexgssInit(trans::KexgssInit<'b>),
+    KexgssContinue(trans::KexgssContinue<'b>),
+    KexgssComplete(trans::KexgssComplete<'b>),
+    KexgssHostkey(trans::KexgssHostkey<'b>),
+    KexgssError(trans::KexgssError<'b>),
+    KexgssGroupreq(trans::KexgssGroupreq),
+    KexgssGroup(trans::KexgssGroup<'b>),
+    UserauthRequest(userauth::Request<'b>),
+    UserauthFailure(userauth::Failure<'b>),
+    UserauthSuccess(userauth::Success),
+    UserauthBanner(userauth::Banner<'b>),
+    UserauthPkOk(userauth::PkOk<'b>),
+    UserauthPasswdChangereq(userauth::PasswdChangereq<'b>),
+    UserauthInfoRequest(userauth::InfoRequest<'b>),
+    UserauthInfoResponse(userauth::InfoResponse),
+    UserauthGssapiResponse(userauth::GssapiResponse<'b>),
+    UserauthGssapiToken(userauth::GssapiToken<'b>),
+    UserauthGssapiExchangeComplete(userauth::GssapiExchangeComplete),
+    UserauthGssapiError(userauth::GssapiError<'b>),
+    UserauthGssapiErrtok(userauth::GssapiErrtok<'b>),
+    UserauthGssapiMic(userauth::GssapiMic<'b>),
+    GlobalRequest(connect::GlobalRequest<'b>),
+    RequestSuccess(connect::RequestSuccess),
+    ForwardingSuccess(connect::ForwardingSuccess),
+    RequestFailure(connect::RequestFailure),
+    ChannelOpen(connect::ChannelOpen<'b>),
+    ChannelOpenConfirmation(connect::ChannelOpenConfirmation),
+    ChannelOpenFailure(connect::ChannelOpenFailure<'b>),
+    ChannelWindowAdjust(connect::ChannelWindowAdjust),
+    ChannelData(connect::ChannelData<'b>),
+    ChannelExtendedData(connect::ChannelExtendedData<'b>),
+    ChannelEof(connect::ChannelEof),
+    ChannelClose(connect::ChannelClose),
+    ChannelRequest(connect::ChannelRequest<'b>),
+    ChannelSuccess(connect::ChannelSuccess),
+    ChannelFailure(connect::ChannelFailure),
+    Ping(trans::Ping<'b>),
+    Pong(trans::Pong<'b>),
+}
+
+impl Message<'_> {
+    /// Get the _message number_ of the [`Message`].
+    pub fn number(&self) -> u8 {
+        match self {
+            Self::Disconnect(_) => 1,
+            Self::Ignore(_) => 2,
+            Self::Unimplemented(_) => 3,
+            Self::Debug(_) => 4,
+            Self::ServiceRequest(_) => 5,
+            Self::ServiceAccept(_) => 6,
+            Self::ExtInfo(_) => 7,
+            Self::KexInit(_) => 20,
+            Self::NewKeys(_) => 21,
+            Self::KexdhInit(_)
+            | Self::KexEcdhInit(_)
+            | Self::KexDhGexRequestOld(_)
+            | Self::KexgssInit(_) => 30,
+            Self::KexdhReply(_)
+            | Self::KexEcdhReply(_)
+            | Self::KexDhGexGroup(_)
+            | Self::KexgssContinue(_) => 31,
+            Self::KexDhGexInit(_) | Self::KexgssComplete(_) => 32,
+            Self::KexDhGexReply(_) | Self::KexgssHostkey(_) => 33,
+            Self::KexDhGexRequest(_) | Self::KexgssError(_) => 34,
+            Self::KexgssGroupreq(_) => 40,
+            Self::KexgssGroup(_) => 41,
+            Self::UserauthRequest(_) => 50,
+            Self::UserauthFailure(_) => 51,
+            Self::UserauthSuccess(_) => 52,
+            Self::UserauthBanner(_) => 53,
+            Self::UserauthPkOk(_)
+            | Self::UserauthPasswdChangereq(_)
+            | Self::UserauthInfoRequest(_)
+            | Self::UserauthGssapiResponse(_) => 60,
+            Self::UserauthInfoResponse(_) | Self::UserauthGssapiToken(_) => 61,
+            Self::UserauthGssapiExchangeComplete(_) => 63,
+            Self::UserauthGssapiError(_) => 64,
+            Self::UserauthGssapiErrtok(_) => 65,
+            Self::UserauthGssapiMic(_) => 66,
+            Self::GlobalRequest(_) => 80,
+            Self::RequestSuccess(_) | Self::ForwardingSuccess(_) => 81,
+            Self::RequestFailure(_) => 82,
+            Self::ChannelOpen(_) => 90,
+            Self::ChannelOpenConfirmation(_) => 91,
+            Self::ChannelOpenFailure(_) => 92,
+            Self::ChannelWindowAdjust(_) => 93,
+            Self::ChannelData(_) => 94,
+            Self::ChannelExtendedData(_) => 95,
+            Self::ChannelEof(_) => 96,
+            Self::ChannelClose(_) => 97,
+            Self::ChannelRequest(_) => 98,
+            Self::ChannelSuccess(_) => 99,
+            Self::ChannelFailure(_) => 100,
+            Self::Ping(_) => 192,
+            Self::Pong(_) => 193,
+            Self::Unknown { number, .. } => *number,
+        }
+    }
+}
+
+impl BinRead for Message<'_> {
+    type Args<'a> = &'a MessageContext;
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: Endian,
+        context: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        let pos = reader.stream_position()?;
+        let number = u8::read_options(reader, endian, ())?;
+        reader.seek(SeekFrom::Start(pos))?;
+
+        let ambiguous = |what: &str| binrw::Error::Custom {
+            pos,
+            err: Box::new(format!(
+                "Ambiguous message number {number}, the {what} is missing from the context"
+            )),
+        };
+
+        let message = match number {
+            1 => Self::Disconnect(BinRead::read_options(reader, endian, ())?),
+            2 => Self::Ignore(BinRead::read_options(reader, endian, ())?),
+            3 => Self::Unimplemented(BinRead::read_options(reader, endian, ())?),
+            4 => Self::Debug(BinRead::read_options(reader, endian, ())?),
+            5 => Self::ServiceRequest(BinRead::read_options(reader, endian, ())?),
+            6 => Self::ServiceAccept(BinRead::read_options(reader, endian, ())?),
+            7 => Self::ExtInfo(BinRead::read_options(reader, endian, ())?),
+            20 => Self::KexInit(BinRead::read_options(reader, endian, ())?),
+            21 => Self::NewKeys(BinRead::read_options(reader, endian, ())?),
+            30 => match context
+                .kex
+                .ok_or_else(|| ambiguous("key-exchange method"))?
+            {
+                KexFamily::Dh => Self::KexdhInit(BinRead::read_options(reader, endian, ())?),
+                KexFamily::Ecdh => Self::KexEcdhInit(BinRead::read_options(reader, endian, ())?),
+                KexFamily::Gex => {
+                    Self::KexDhGexRequestOld(BinRead::read_options(reader, endian, ())?)
+                }
+                KexFamily::Gss => Self::KexgssInit(BinRead::read_options(reader, endian, ())?),
+            },
+            31 => match context
+                .kex
+                .ok_or_else(|| ambiguous("key-exchange method"))?
+            {
+                KexFamily::Dh => Self::KexdhReply(BinRead::read_options(reader, endian, ())?),
+                KexFamily::Ecdh => Self::KexEcdhReply(BinRead::read_options(reader, endian, ())?),
+                KexFamily::Gex => Self::KexDhGexGroup(BinRead::read_options(reader, endian, ())?),
+                KexFamily::Gss => Self::KexgssContinue(BinRead::read_options(reader, endian, ())?),
+            },
+            32..=34 => match context
+                .kex
+                .ok_or_else(|| ambiguous("key-exchange method"))?
+            {
+                KexFamily::Gss => match number {
+                    32 => Self::KexgssComplete(BinRead::read_options(reader, endian, ())?),
+                    33 => Self::KexgssHostkey(BinRead::read_options(reader, endian, ())?),
+                    _ => Self::KexgssError(BinRead::read_options(reader, endian, ())?),
+                },
+                KexFamily::Gex => match number {
+                    32 => Self::KexDhGexInit(BinRead::read_options(reader, endian, ())?),
+                    33 => Self::KexDhGexReply(BinRead::read_options(reader, endian, ())?),
+                    _ => Self::KexDhGexRequest(BinRead::read_options(reader, endian, ())?),
+                },
+                // These numbers are not defined by the other key-exchange methods
+                KexFamily::Dh | KexFamily::Ecdh => Self::read_unknown(reader, number)?,
+            },
+            40 => Self::KexgssGroupreq(BinRead::read_options(reader, endian, ())?),
+            41 => Self::KexgssGroup(BinRead::read_options(reader, endian, ())?),
+            50 => Self::UserauthRequest(BinRead::read_options(reader, endian, ())?),
+            51 => Self::UserauthFailure(BinRead::read_options(reader, endian, ())?),
+            52 => Self::UserauthSuccess(BinRead::read_options(reader, endian, ())?),
+            53 => Self::UserauthBanner(BinRead::read_options(reader, endian, ())?),
+            60 => match &context.userauth {
+                Some(method) if *method == userauth::Method::PUBLICKEY => {
+                    Self::UserauthPkOk(BinRead::read_options(reader, endian, ())?)
+                }
+                Some(method) if *method == userauth::Method::PASSWORD => {
+                    Self::UserauthPasswdChangereq(BinRead::read_options(reader, endian, ())?)
+                }
+                Some(method) if *method == userauth::Method::KEYBOARD_INTERACTIVE => {
+                    Self::UserauthInfoRequest(BinRead::read_options(reader, endian, ())?)
+                }
+                Some(method) if *method == userauth::Method::GSSAPI_WITH_MIC => {
+                    Self::UserauthGssapiResponse(BinRead::read_options(reader, endian, ())?)
+                }
+                _ => return Err(ambiguous("authentication method")),
+            },
+            61 => match &context.userauth {
+                Some(method) if *method == userauth::Method::KEYBOARD_INTERACTIVE => {
+                    Self::UserauthInfoResponse(BinRead::read_options(reader, endian, ())?)
+                }
+                Some(method) if *method == userauth::Method::GSSAPI_WITH_MIC => {
+                    Self::UserauthGssapiToken(BinRead::read_options(reader, endian, ())?)
+                }
+                _ => return Err(ambiguous("authentication method")),
+            },
+            63 => Self::UserauthGssapiExchangeComplete(BinRead::read_options(reader, endian, ())?),
+            64 => Self::UserauthGssapiError(BinRead::read_options(reader, endian, ())?),
+            65 => Self::UserauthGssapiErrtok(BinRead::read_options(reader, endian, ())?),
+            66 => Self::UserauthGssapiMic(BinRead::read_options(reader, endian, ())?),
+            80 => Self::GlobalRequest(BinRead::read_options(reader, endian, ())?),
+            81 => match connect::ForwardingSuccess::read_options(reader, endian, ()) {
+                Ok(message) => Self::ForwardingSuccess(message),
+                Err(_) => {
+                    // The request-specific data is absent, rewind and parse the empty body
+                    reader.seek(SeekFrom::Start(pos))?;
+
+                    Self::RequestSuccess(BinRead::read_options(reader, endian, ())?)
+                }
+            },
+            82 => Self::RequestFailure(BinRead::read_options(reader, endian, ())?),
+            90 => Self::ChannelOpen(BinRead::read_options(reader, endian, ())?),
+            91 => Self::ChannelOpenConfirmation(BinRead::read_options(reader, endian, ())?),
+            92 => Self::ChannelOpenFailure(BinRead::read_options(reader, endian, ())?),
+            93 => Self::ChannelWindowAdjust(BinRead::read_options(reader, endian, ())?),
+            94 => Self::ChannelData(BinRead::read_options(reader, endian, ())?),
+            95 => Self::ChannelExtendedData(BinRead::read_options(reader, endian, ())?),
+            96 => Self::ChannelEof(BinRead::read_options(reader, endian, ())?),
+            97 => Self::ChannelClose(BinRead::read_options(reader, endian, ())?),
+            98 => Self::ChannelRequest(BinRead::read_options(reader, endian, ())?),
+            99 => Self::ChannelSuccess(BinRead::read_options(reader, endian, ())?),
+            100 => Self::ChannelFailure(BinRead::read_options(reader, endian, ())?),
+            192 => Self::Ping(BinRead::read_options(reader, endian, ())?),
+            193 => Self::Pong(BinRead::read_options(reader, endian, ())?),
+            number => Self::read_unknown(reader, number)?,
+        };
+
+        Ok(message)
+    }
+}
+
+impl Message<'_> {
+    fn read_unknown<R: std::io::Read + std::io::Seek>(
+        reader: &mut R,
+        number: u8,
+    ) -> binrw::BinResult<Self> {
+        reader.seek(SeekFrom::Current(std::mem::size_of_val(&number) as i64))?;
+
+        let mut payload = Vec::new();
+        reader.read_to_end(&mut payload)?;
+
+        Ok(Self::Unknown { number, payload })
+    }
+}
+
+impl ReadEndian for Message<'_> {
+    const ENDIAN: EndianKind = EndianKind::Endian(Endian::Big);
+}
+
+impl BinWrite for Message<'_> {
+    type Args<'a> = ();
+
+    fn write_options<W: std::io::Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: Endian,
+        args: Self::Args<'_>,
+    ) -> BinResult<()> {
+        match self {
+            Self::Disconnect(message) => message.write_options(writer, endian, args),
+            Self::Ignore(message) => message.write_options(writer, endian, args),
+            Self::Unimplemented(message) => message.write_options(writer, endian, args),
+            Self::Debug(message) => message.write_options(writer, endian, args),
+            Self::ServiceRequest(message) => message.write_options(writer, endian, args),
+            Self::ServiceAccept(message) => message.write_options(writer, endian, args),
+            Self::ExtInfo(message) => message.write_options(writer, endian, args),
+            Self::KexInit(message) => message.write_options(writer, endian, args),
+            Self::NewKeys(message) => message.write_options(writer, endian, args),
+            Self::KexdhInit(message) => message.write_options(writer, endian, args),
+            Self::KexdhReply(message) => message.write_options(writer, endian, args),
+            Self::KexEcdhInit(message) => message.write_options(writer, endian, args),
+            Self::KexEcdhReply(message) => message.write_options(writer, endian, args),
+            Self::KexDhGexRequestOld(message) => message.write_options(writer, endian, args),
+            Self::KexDhGexGroup(message) => message.write_options(writer, endian, args),
+            Self::KexDhGexInit(message) => message.write_options(writer, endian, args),
+            Self::KexDhGexReply(message) => message.write_options(writer, endian, args),
+            Self::KexDhGexRequest(message) => message.write_options(writer, endian, args),
+            Self::KexgssInit(message) => message.write_options(writer, endian, args),
+            Self::KexgssContinue(message) => message.write_options(writer, endian, args),
+            Self::KexgssComplete(message) => message.write_options(writer, endian, args),
+            Self::KexgssHostkey(message) => message.write_options(writer, endian, args),
+            Self::KexgssError(message) => message.write_options(writer, endian, args),
+            Self::KexgssGroupreq(message) => message.write_options(writer, endian, args),
+            Self::KexgssGroup(message) => message.write_options(writer, endian, args),
+            Self::UserauthRequest(message) => message.write_options(writer, endian, args),
+            Self::UserauthFailure(message) => message.write_options(writer, endian, args),
+            Self::UserauthSuccess(message) => message.write_options(writer, endian, args),
+            Self::UserauthBanner(message) => message.write_options(writer, endian, args),
+            Self::UserauthPkOk(message) => message.write_options(writer, endian, args),
+            Self::UserauthPasswdChangereq(message) => message.write_options(writer, endian, args),
+            Self::UserauthInfoRequest(message) => message.write_options(writer, endian, args),
+            Self::UserauthInfoResponse(message) => message.write_options(writer, endian, args),
+            Self::UserauthGssapiResponse(message) => message.write_options(writer, endian, args),
+            Self::UserauthGssapiToken(message) => message.write_options(writer, endian, args),
+            Self::UserauthGssapiExchangeComplete(message) => {
+                message.write_options(writer, endian, args)
+            }
+            Self::UserauthGssapiError(message) => message.write_options(writer, endian, args),
+            Self::UserauthGssapiErrtok(message) => message.write_options(writer, endian, args),
+            Self::UserauthGssapiMic(message) => message.write_options(writer, endian, args),
+            Self::GlobalRequest(message) => message.write_options(writer, endian, args),
+            Self::RequestSuccess(message) => message.write_options(writer, endian, args),
+            Self::ForwardingSuccess(message) => message.write_options(writer, endian, args),
+            Self::RequestFailure(message) => message.write_options(writer, endian, args),
+            Self::ChannelOpen(message) => message.write_options(writer, endian, args),
+            Self::ChannelOpenConfirmation(message) => message.write_options(writer, endian, args),
+            Self::ChannelOpenFailure(message) => message.write_options(writer, endian, args),
+            Self::ChannelWindowAdjust(message) => message.write_options(writer, endian, args),
+            Self::ChannelData(message) => message.write_options(writer, endian, args),
+            Self::ChannelExtendedData(message) => message.write_options(writer, endian, args),
+            Self::ChannelEof(message) => message.write_options(writer, endian, args),
+            Self::ChannelClose(message) => message.write_options(writer, endian, args),
+            Self::ChannelRequest(message) => message.write_options(writer, endian, args),
+            Self::ChannelSuccess(message) => message.write_options(writer, endian, args),
+            Self::ChannelFailure(message) => message.write_options(writer, endian, args),
+            Self::Ping(message) => message.write_options(writer, endian, args),
+            Self::Pong(message) => message.write_options(writer, endian, args),
+            Self::Unknown { number, payload } => {
+                number.write_options(writer, endian, args)?;
+                payload.write_options(writer, endian, args)
+            }
+        }
+    }
+}
+
+impl WriteEndian for Message<'_> {
+    const ENDIAN: EndianKind = EndianKind::Endian(Endian::Big);
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{arch, IntoPacket, Packet};
+
+    #[rstest]
+    #[case(trans::Ignore { data: arch::Bytes::borrowed(b"ignored") }.into())]
+    #[case(trans::Unimplemented { seq: 42 }.into())]
+    #[case(trans::NewKeys.into())]
+    #[case(trans::ExtInfo { extensions: vec![trans::Extension::Ping { version: arch::ascii!("0") }] }.into())]
+    #[case(userauth::Success.into())]
+    #[case(connect::RequestSuccess.into())]
+    #[case(connect::ForwardingSuccess { bound_port: 2222 }.into())]
+    #[case(connect::ChannelData { recipient_channel: 1, data: arch::Bytes::borrowed(b"data") }.into())]
+    #[case(userauth::Request {
+        username: "user".into(),
+        service_name: arch::ascii!("ssh-connection"),
+        method: userauth::Method::GssapiWithMic { mechanisms: vec![arch::Bytes::borrowed(&[0x06, 0x01, 0x2a])] },
+    }.into())]
+    #[case(userauth::GssapiMic { mic: arch::Bytes::borrowed(b"mic") }.into())]
+    #[case(trans::KexgssGroupreq { min: 2048, n: 4096, max: 8192 }.into())]
+    #[case(trans::Ping { data: arch::Bytes::borrowed(b"ping") }.into())]
+    #[case(trans::Pong { data: arch::Bytes::borrowed(b"pong") }.into())]
+    #[case(Message::Unknown { number: 254, payload: vec![0xca, 0xfe] })]
+    fn it_dispatches_consistently(#[case] message: Message<'static>) {
+        let packet = (&message).into_packet();
+        let decoded = packet.message(&Default::default()).unwrap();
+
+        assert_eq!(decoded.number(), message.number());
+        assert_eq!((&decoded).into_packet().payload, packet.payload);
+    }
+
+    #[rstest]
+    #[case(userauth::Method::PUBLICKEY, 60)]
+    #[case(userauth::Method::PASSWORD, 60)]
+    #[case(userauth::Method::KEYBOARD_INTERACTIVE, 60)]
+    #[case(userauth::Method::KEYBOARD_INTERACTIVE, 61)]
+    #[case(userauth::Method::GSSAPI_WITH_MIC, 60)]
+    #[case(userauth::Method::GSSAPI_WITH_MIC, 61)]
+    fn it_disambiguates_with_userauth(#[case] method: arch::Ascii<'static>, #[case] number: u8) {
+        let packet = Packet {
+            payload: [&[number][..], &[0; 16]].concat(),
+        };
+        let context = MessageContext {
+            userauth: Some(method.clone()),
+            ..Default::default()
+        };
+
+        let message = packet.message(&context).unwrap();
+        match message {
+            Message::UserauthPkOk(_) => assert_eq!(method, userauth::Method::PUBLICKEY),
+            Message::UserauthPasswdChangereq(_) => assert_eq!(method, userauth::Method::PASSWORD),
+            Message::UserauthInfoRequest(_) | Message::UserauthInfoResponse(_) => {
+                assert_eq!(method, userauth::Method::KEYBOARD_INTERACTIVE)
+            }
+            Message::UserauthGssapiResponse(_) | Message::UserauthGssapiToken(_) => {
+                assert_eq!(method, userauth::Method::GSSAPI_WITH_MIC)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[rstest]
+    #[case(KexFamily::Dh)]
+    #[case(KexFamily::Ecdh)]
+    #[case(KexFamily::Gex)]
+    #[case(KexFamily::Gss)]
+    fn it_disambiguates_with_kex(#[case] kex: KexFamily) {
+        let packet = Packet {
+            payload: vec![30, 0, 0, 0, 1, 0x42, 0, 0, 0, 0],
+        };
+        let context = MessageContext {
+            kex: Some(kex),
+            ..Default::default()
+        };
+
+        let message = packet.message(&context).unwrap();
+        match kex {
+            KexFamily::Dh => assert!(matches!(message, Message::KexdhInit(_))),
+            KexFamily::Ecdh => assert!(matches!(message, Message::KexEcdhInit(_))),
+            KexFamily::Gex => assert!(matches!(message, Message::KexDhGexRequestOld(_))),
+            KexFamily::Gss => assert!(matches!(message, Message::KexgssInit(_))),
+        }
+    }
+
+    #[rstest]
+    #[case(KexFamily::Dh, 32)]
+    #[case(KexFamily::Dh, 33)]
+    #[case(KexFamily::Ecdh, 34)]
+    fn it_rejects_gex_outside_gex(#[case] kex: KexFamily, #[case] number: u8) {
+        let packet = Packet {
+            payload: [&[number][..], &[0; 16]].concat(),
+        };
+        let context = MessageContext {
+            kex: Some(kex),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            packet.message(&context).unwrap(),
+            Message::Unknown { number: found, .. } if found == number
+        ));
+    }
+
+    #[rstest]
+    #[case(30)]
+    #[case(31)]
+    #[case(32)]
+    #[case(60)]
+    #[case(61)]
+    fn it_rejects_ambiguous_without_context(#[case] number: u8) {
+        let packet = Packet {
+            payload: [&[number][..], &[0; 16]].concat(),
+        };
+
+        packet.message(&Default::default()).unwrap_err();
+    }
+
+    #[test]
+    fn it_falls_back_to_unknown() {
+        let packet = Packet {
+            payload: vec![255, 1, 2, 3],
+        };
+
+        assert!(matches!(
+            packet.message(&Default::default()).unwrap(),
+            Message::Unknown { number: 255, payload } if payload == [1, 2, 3]
+        ));
+    }
+}
@@ -0,0 +1,76 @@
+use std::{io::Cursor, num::NonZeroU32};
+
+use binrw::BinRead;
+
+#[cfg(doc)]
+use super::Packet;
+
+/// A type which can be read from a borrowed buffer without copying the `string`s it contains,
+/// as opposed to [`BinRead`], which always produces owned buffers.
+///
+/// This is most useful for the messages carrying large amounts of data, such as the
+/// [`crate::connect::ChannelData`] message, see [`Packet::borrow_as`].
+pub trait BorrowRead<'b>: Sized {
+    /// Read `Self` from the `cursor`, borrowing from its underlying buffer.
+    fn borrow_read(cursor: &mut Cursor<&'b [u8]>) -> binrw::BinResult<Self>;
+}
+
+macro_rules! primitives {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl BorrowRead<'_> for $ty {
+                fn borrow_read(cursor: &mut Cursor<&[u8]>) -> binrw::BinResult<Self> {
+                    Self::read_be(cursor)
+                }
+            }
+        )+
+    };
+}
+
+primitives!(u8, u32, u64, NonZeroU32, crate::arch::Bool);
+
+/// Read the `magic` _message number_ from the `cursor`, as [`BinRead`] would.
+pub(crate) fn read_magic(cursor: &mut Cursor<&[u8]>, magic: u8) -> binrw::BinResult<()> {
+    let pos = cursor.position();
+    let found = u8::borrow_read(cursor)?;
+
+    if found == magic {
+        Ok(())
+    } else {
+        Err(binrw::Error::BadMagic {
+            pos,
+            found: Box::new(found),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::arch;
+
+    #[rstest]
+    #[case(&[0, 0, 0, 2, b'o', b'k'], true, true, true)]
+    #[case(&[0, 0, 0, 2, 0xc3, 0xa9], true, true, false)]
+    #[case(&[0, 0, 0, 2, 0xff, 0xff], true, false, false)]
+    #[case(&[0, 0, 0, 3, b'o', b'k'], false, false, false)]
+    fn it_reads_strings(
+        #[case] buf: &[u8],
+        #[case] bytes: bool,
+        #[case] utf8: bool,
+        #[case] ascii: bool,
+    ) {
+        assert_eq!(
+            arch::Bytes::borrow_read(&mut Cursor::new(buf)).is_ok(),
+            bytes
+        );
+        assert_eq!(arch::Utf8::borrow_read(&mut Cursor::new(buf)).is_ok(), utf8);
+        assert_eq!(
+            arch::Ascii::borrow_read(&mut Cursor::new(buf)).is_ok(),
+            ascii
+        );
+    }
+}
@@ -1,7 +1,7 @@
-use super::{Mac, PACKET_MIN_SIZE};
+use super::{Mac, PACKET_MAX_SIZE, PACKET_MIN_SIZE};
 
 #[cfg(doc)]
-use super::Packet;
+use super::{Packet, PacketEncoder};
 
 const MIN_PAD_SIZE: usize = 4;
 const MIN_ALIGN: usize = 8;
@@ -21,28 +21,69 @@ pub trait CipherCore {
     /// The size of a [`CipherCore`]'s block.
     fn block_size(&self) -> usize;
 
-    /// Calculate the necessary padding size for the provided payload `size`.
+    /// Whether the cipher is an _AEAD_ cipher, keeping the packet length unencrypted
+    /// as _additional authenticated data_ and producing a tag in place of the
+    /// _Message Authentication Code_, of the size described by [`CipherCore::mac`].
+    ///
+    /// see <https://datatracker.ietf.org/doc/html/rfc5647#section-7>.
+    fn aead(&self) -> bool {
+        false
+    }
+
+    /// The maximum size of the packets opened and sealed by the [`CipherCore`],
+    /// excluding the packet length and the _Message Authentication Code_.
+    ///
+    /// The protocol requires packets of at least 35000 bytes to be supported, this defaults
+    /// to [`PACKET_MAX_SIZE`] but may be raised when agreed upon by the application,
+    /// such as for the 256 KiB packets of OpenSSH's SFTP implementation.
+    ///
+    /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-6.1>.
+    fn max_packet_size(&self) -> usize {
+        PACKET_MAX_SIZE
+    }
+
+    /// The minimum size of the packets sealed and opened by the [`CipherCore`],
+    /// packet length included and _Message Authentication Code_ excluded,
+    /// defaulting to [`PACKET_MIN_SIZE`].
+    ///
+    /// Sealed packets are padded up to this size, and smaller opened packets are rejected,
+    /// save for the 4 bytes of the packet length when [`Mac::etm`] or [`CipherCore::aead`]
+    /// is `true`, since OpenSSH leaves them out of the padding computation.
+    fn min_packet_size(&self) -> usize {
+        PACKET_MIN_SIZE
+    }
+
+    /// Calculate the necessary padding size for the provided payload `size`,
+    /// for the packet to reach both the block size and [`CipherCore::min_packet_size`],
+    /// within the limit of 255 bytes of padding.
     fn padding(&self, payload: usize) -> u8 {
         let align = self.block_size().max(MIN_ALIGN);
 
-        let size = if self.mac().etm() {
-            std::mem::size_of::<u8>() + payload
+        // The packet length is not part of the aligned data for these ciphers
+        let (head, size) = if self.mac().etm() || self.aead() {
+            (
+                std::mem::size_of::<u32>(),
+                std::mem::size_of::<u8>() + payload,
+            )
         } else {
-            std::mem::size_of::<u32>() + std::mem::size_of::<u8>() + payload
+            (
+                0,
+                std::mem::size_of::<u32>() + std::mem::size_of::<u8>() + payload,
+            )
         };
-        let padding = align - size % align;
+        let min = self
+            .block_size()
+            .max(self.min_packet_size())
+            .saturating_sub(head);
 
-        let padding = if padding < MIN_PAD_SIZE {
-            padding + align
-        } else {
-            padding
-        };
+        let mut padding = (size + MIN_PAD_SIZE).max(min).div_ceil(align) * align - size;
 
-        if size + padding < self.block_size().max(PACKET_MIN_SIZE) {
-            (padding + align) as u8
-        } else {
-            padding as u8
+        // The padding length is a single byte, drop the blocks that don't fit
+        while padding > u8::MAX as usize {
+            padding -= align;
         }
+
+        padding as u8
     }
 }
 
@@ -51,11 +92,38 @@ pub trait OpeningCipher: CipherCore {
     /// Decrypt the received `buf` using the [`OpeningCipher`].
     fn decrypt<B: AsMut<[u8]>>(&mut self, buf: B) -> Result<(), Self::Err>;
 
+    /// Decrypt the packet length from the first 4 received bytes `buf`, with the
+    /// sequence number `seq`, without altering them for the authentication.
+    ///
+    /// This is only called for ciphers where [`Mac::etm`] or [`CipherCore::aead`] is `true`,
+    /// and by default reads the length as unencrypted, as opposed to
+    /// `chacha20-poly1305@openssh.com` which encrypts it with a separate key.
+    fn decrypt_length(&mut self, buf: [u8; 4], seq: u32) -> Result<u32, Self::Err> {
+        let _ = seq;
+
+        Ok(u32::from_be_bytes(buf))
+    }
+
     /// Compare the received `buf` against the received _Message Authentication Code_.
     fn open<B: AsRef<[u8]>>(&mut self, buf: B, mac: Vec<u8>, seq: u32) -> Result<(), Self::Err>;
 
     /// Decompress the received `buf` using the [`OpeningCipher`].
     fn decompress(&mut self, buf: Vec<u8>) -> Result<Vec<u8>, Self::Err>;
+
+    /// Authenticate the received `buf` against the received `tag` and decrypt it in place
+    /// using the _AEAD_ [`OpeningCipher`], the `buf` being prefixed with the unencrypted packet length.
+    ///
+    /// This is only called for ciphers where [`CipherCore::aead`] is `true`.
+    fn open_aead<B: AsMut<[u8]>>(
+        &mut self,
+        buf: B,
+        tag: Vec<u8>,
+        seq: u32,
+    ) -> Result<(), Self::Err> {
+        let _ = (buf, tag, seq);
+
+        Err(not_aead())?
+    }
 }
 
 /// A cipher able to `seal` a payload to create a [`Packet`].
@@ -67,9 +135,98 @@ pub trait SealingCipher: CipherCore {
     /// by increasing it by `padding` bytes and prefixing the `buf` it with it's len.
     fn pad(&mut self, buf: Vec<u8>, padding: u8) -> Result<Vec<u8>, Self::Err>;
 
+    /// Compress the `buf` by appending it to `out`, using the [`SealingCipher`].
+    ///
+    /// This is used by the [`PacketEncoder`] to avoid intermediate allocations,
+    /// and by default delegates to [`SealingCipher::compress`].
+    fn compress_into(&mut self, buf: &[u8], out: &mut Vec<u8>) -> Result<(), Self::Err> {
+        out.extend_from_slice(&self.compress(buf)?);
+
+        Ok(())
+    }
+
+    /// Fill the `padding` of a packet with random data, using the [`SealingCipher`].
+    ///
+    /// This is used by the [`PacketEncoder`] to avoid intermediate allocations,
+    /// and by default delegates to [`SealingCipher::pad`].
+    fn fill_padding(&mut self, padding: &mut [u8]) -> Result<(), Self::Err> {
+        let padded = self.pad(Vec::new(), padding.len() as u8)?;
+
+        copy_exact(padding, padded.get(1..).unwrap_or_default())
+    }
+
     /// Encrypt the `buf` using using the [`SealingCipher`].
     fn encrypt<B: AsMut<[u8]>>(&mut self, buf: B) -> Result<(), Self::Err>;
 
+    /// Encrypt the packet length `len` with the sequence number `seq`,
+    /// before the packet gets authenticated.
+    ///
+    /// This is only called for ciphers where [`Mac::etm`] or [`CipherCore::aead`] is `true`,
+    /// and by default leaves the length unencrypted, as opposed to
+    /// `chacha20-poly1305@openssh.com` which encrypts it with a separate key.
+    fn encrypt_length(&mut self, len: u32, seq: u32) -> Result<[u8; 4], Self::Err> {
+        let _ = seq;
+
+        Ok(len.to_be_bytes())
+    }
+
     /// Generate a seal from the HMAC algorithm to produce a _Message Authentication Code_.
     fn seal<B: AsRef<[u8]>>(&mut self, buf: B, seq: u32) -> Result<Vec<u8>, Self::Err>;
+
+    /// Generate a seal from the HMAC algorithm into `mac`, of the size described by [`CipherCore::mac`].
+    ///
+    /// This is used by the [`PacketEncoder`] to avoid intermediate allocations,
+    /// and by default delegates to [`SealingCipher::seal`].
+    fn seal_into(&mut self, buf: &[u8], seq: u32, mac: &mut [u8]) -> Result<(), Self::Err> {
+        copy_exact(mac, &self.seal(buf, seq)?)
+    }
+
+    /// Encrypt the `buf` in place and produce it's authentication tag using the _AEAD_ [`SealingCipher`],
+    /// the `buf` being prefixed with the unencrypted packet length.
+    ///
+    /// This is only called for ciphers where [`CipherCore::aead`] is `true`.
+    fn seal_aead<B: AsMut<[u8]>>(&mut self, buf: B, seq: u32) -> Result<Vec<u8>, Self::Err> {
+        let _ = (buf, seq);
+
+        Err(not_aead())?
+    }
+
+    /// Encrypt the `buf` in place and produce its authentication tag into `tag`,
+    /// using the _AEAD_ [`SealingCipher`], see [`SealingCipher::seal_aead`].
+    ///
+    /// This is used by the [`PacketEncoder`] to avoid intermediate allocations,
+    /// and by default delegates to [`SealingCipher::seal_aead`].
+    fn seal_aead_into(
+        &mut self,
+        buf: &mut [u8],
+        seq: u32,
+        tag: &mut [u8],
+    ) -> Result<(), Self::Err> {
+        copy_exact(tag, &self.seal_aead(buf, seq)?)
+    }
+}
+
+/// Copy the `src` into the `dst`, failing if the sizes don't match.
+fn copy_exact<E: From<binrw::Error>>(dst: &mut [u8], src: &[u8]) -> Result<(), E> {
+    if dst.len() == src.len() {
+        dst.copy_from_slice(src);
+
+        Ok(())
+    } else {
+        Err(binrw::Error::Custom {
+            pos: 0x0,
+            err: Box::new(format!(
+                "The cipher produced {} bytes where {} were expected",
+                src.len(),
+                dst.len()
+            )),
+        })?
+    }
+}
+
+fn not_aead() -> binrw::Error {
+    binrw::Error::Custom {
+        pos: 0x0,
+        err: Box::new("The cipher is not an AEAD cipher"),
+    }
 }
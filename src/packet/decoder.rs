@@ -0,0 +1,123 @@
+use super::{Mac, OpeningCipher, Packet};
+
+#[derive(Debug, Default, Clone, Copy)]
+enum State {
+    /// Waiting for the first block of the packet.
+    #[default]
+    Head,
+
+    /// The first block has been opened, waiting for the
+    /// rest of the packet and it's _Message Authentication Code_.
+    Body { len: u32 },
+}
+
+/// A _sans-IO_ incremental [`Packet`] decoder, fed with arbitrary chunks of bytes.
+///
+/// The decoder never reads ahead of the packet being decoded, which allows
+/// the [`OpeningCipher`] to be swapped between two calls to [`PacketDecoder::decode`].
+///
+/// If decoding fails, the decoder's state is left unspecified and
+/// the stream must be considered desynchronized.
+#[derive(Debug, Default, Clone)]
+pub struct PacketDecoder {
+    buf: Vec<u8>,
+    state: State,
+}
+
+impl PacketDecoder {
+    /// Create a new empty [`PacketDecoder`].
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Append the received `data` to the decoder's buffer.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Whether the decoder holds no buffered data, nor any partially decoded packet.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty() && matches!(self.state, State::Head)
+    }
+
+    /// The amount of buffered bytes, not yet decoded.
+    #[cfg(feature = "futures")]
+    pub(crate) fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Try to decode a [`Packet`] from the buffered data with the `cipher`,
+    /// returning [`None`] if more data needs to be fed to complete it.
+    pub fn decode<C: OpeningCipher>(
+        &mut self,
+        cipher: &mut C,
+        seq: u32,
+    ) -> Result<Option<Packet>, C::Err> {
+        let len = match self.state {
+            State::Body { len } => len,
+            State::Head => {
+                if self.buf.len() < cipher.block_size() {
+                    return Ok(None);
+                }
+
+                let len = Packet::open_head(cipher, &mut self.buf[..cipher.block_size()], seq)?;
+                self.state = State::Body { len };
+
+                len
+            }
+        };
+
+        let size = std::mem::size_of_val(&len) + len as usize;
+        if self.buf.len() < size + cipher.mac().size() {
+            return Ok(None);
+        }
+
+        let rest = self.buf.split_off(size + cipher.mac().size());
+        let mut buf = std::mem::replace(&mut self.buf, rest);
+        let mac = buf.split_off(size);
+        self.state = State::Head;
+
+        Packet::open_body(cipher, buf, mac, seq).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::packet::tests::Plain;
+
+    #[rstest]
+    #[case(Plain { etm: false, aead: false }, 1)]
+    #[case(Plain { etm: true, aead: false }, 1)]
+    #[case(Plain { etm: false, aead: false }, 7)]
+    #[case(Plain { etm: true, aead: false }, 4096)]
+    #[case(Plain { etm: false, aead: true }, 3)]
+    fn it_decodes_chunked(#[case] mut cipher: Plain, #[case] chunk: usize) {
+        let payloads = [vec![], vec![21], vec![94; 300]];
+
+        let mut buf = Vec::new();
+        for (seq, payload) in payloads.iter().enumerate() {
+            Packet {
+                payload: payload.clone(),
+            }
+            .to_sync_writer(&mut buf, &mut cipher, seq as u32)
+            .unwrap();
+        }
+
+        let mut decoder = PacketDecoder::new();
+        let mut decoded = Vec::new();
+        for data in buf.chunks(chunk) {
+            decoder.feed(data);
+
+            while let Some(packet) = decoder.decode(&mut cipher, decoded.len() as u32).unwrap() {
+                decoded.push(packet.payload);
+            }
+        }
+
+        assert_eq!(decoded, payloads);
+        assert!(decoder.is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+use binrw::{meta::WriteEndian, BinWrite};
+
+use super::{Packet, SealingCipher};
+
+/// A _sans-IO_ [`Packet`] encoder, sealing payloads into a reusable buffer.
+///
+/// Each packet is appended to the buffer along with its _Message Authentication Code_,
+/// so that many packets can be written at once, and the buffer keeps its capacity
+/// as it gets consumed, so that no allocation occurs per packet in the steady state,
+/// as long as the [`SealingCipher`] implements the `*_into` methods.
+#[derive(Debug, Default, Clone)]
+pub struct PacketEncoder {
+    buf: Vec<u8>,
+    scratch: Vec<u8>,
+}
+
+impl PacketEncoder {
+    /// Create a new empty [`PacketEncoder`].
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Seal the `payload` with the `cipher` and the sequence number `seq`,
+    /// appending the resulting packet to the encoder's buffer.
+    ///
+    /// If sealing fails, the buffer is left untouched, but the `cipher`'s state, such as
+    /// its compression stream, may have advanced and the `cipher` must not be used further.
+    pub fn encode<C: SealingCipher>(
+        &mut self,
+        payload: &[u8],
+        cipher: &mut C,
+        seq: u32,
+    ) -> Result<(), C::Err> {
+        Packet::encode(payload, cipher, seq, &mut self.buf)
+    }
+
+    /// Serialize the `message` and seal it with the `cipher` and the sequence number `seq`,
+    /// appending the resulting packet to the encoder's buffer, see [`PacketEncoder::encode`].
+    pub fn encode_message<T, C>(
+        &mut self,
+        message: &T,
+        cipher: &mut C,
+        seq: u32,
+    ) -> Result<(), C::Err>
+    where
+        T: for<'a> BinWrite<Args<'a> = ()> + WriteEndian,
+        C: SealingCipher,
+    {
+        let mut scratch = std::mem::take(&mut self.scratch);
+
+        scratch.clear();
+        let result = message
+            .write(&mut std::io::Cursor::new(&mut scratch))
+            .map_err(Into::into)
+            .and_then(|()| self.encode(&scratch, cipher, seq));
+
+        self.scratch = scratch;
+
+        result
+    }
+
+    /// The buffered bytes, waiting to be written.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Remove the first `amount` bytes of the buffer, once they've been written.
+    pub fn consume(&mut self, amount: usize) {
+        self.buf.drain(..amount.min(self.buf.len()));
+    }
+
+    /// The amount of buffered bytes.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Whether the encoder holds no buffered bytes.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{packet::tests::Plain, trans, PacketDecoder};
+
+    #[rstest]
+    #[case(Plain { etm: false, aead: false })]
+    #[case(Plain { etm: true, aead: false })]
+    #[case(Plain { etm: false, aead: true })]
+    fn it_matches_writer(#[case] mut cipher: Plain) {
+        let payloads = [vec![], vec![21], vec![94; 300]];
+
+        let mut encoder = PacketEncoder::new();
+        let mut buf = Vec::new();
+        for (seq, payload) in payloads.iter().enumerate() {
+            encoder.encode(payload, &mut cipher, seq as u32).unwrap();
+
+            Packet {
+                payload: payload.clone(),
+            }
+            .to_sync_writer(&mut buf, &mut cipher, seq as u32)
+            .unwrap();
+        }
+        assert_eq!(encoder.buffer(), buf);
+
+        // The buffer keeps its capacity once consumed
+        let capacity = encoder.buf.capacity();
+        encoder.consume(encoder.len());
+        encoder.encode(&payloads[2], &mut cipher, 0).unwrap();
+        assert_eq!(encoder.buf.capacity(), capacity);
+    }
+
+    #[test]
+    fn it_encodes_messages() {
+        let mut cipher = Plain::default();
+        let mut encoder = PacketEncoder::new();
+
+        encoder
+            .encode_message(
+                &trans::Ignore {
+                    data: vec![0x42; 64].into(),
+                },
+                &mut cipher,
+                0,
+            )
+            .unwrap();
+        encoder
+            .encode_message(&trans::NewKeys, &mut cipher, 1)
+            .unwrap();
+
+        let mut decoder = PacketDecoder::new();
+        decoder.feed(encoder.buffer());
+
+        let ignore = decoder.decode(&mut cipher, 0).unwrap().unwrap();
+        assert!(ignore.to::<trans::Ignore>().is_ok());
+        let newkeys = decoder.decode(&mut cipher, 1).unwrap().unwrap();
+        assert!(newkeys.to::<trans::NewKeys>().is_ok());
+        assert!(decoder.is_empty());
+    }
+}
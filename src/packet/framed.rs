@@ -0,0 +1,349 @@
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures::io::{AsyncRead, AsyncWrite};
+
+use super::{OpeningCipher, Packet, PacketDecoder, PacketEncoder, SealingCipher};
+use crate::{trans, Error, Rekey, StrictKex};
+
+/// The size of the chunks read from the underlying stream.
+const CHUNK_SIZE: usize = 4096;
+
+/// A framed transport of [`Packet`]s over an asynchronous `stream`,
+/// implementing both [`futures::Stream`] and [`futures::Sink`].
+///
+/// The [`Framed`] transport owns the opening and sealing ciphers and keeps
+/// track of the sequence numbers for each direction of the transport.
+///
+/// Received packets are only decrypted when polled from the [`futures::Stream`],
+/// and sent packets are sealed as soon as they are fed to the [`futures::Sink`],
+/// this means the ciphers can be safely swapped right after a `SSH_MSG_NEWKEYS`
+/// message has been received or sent, with [`Framed::replace_opening`]
+/// and [`Framed::replace_sealing`] respectively.
+///
+/// The traffic is accounted in a [`Rekey`], reporting when a new key-exchange should be initiated,
+/// and checked by a [`StrictKex`], resetting the sequence numbers on `SSH_MSG_NEWKEYS` once
+/// negotiated with [`Framed::negotiate_strict_kex`].
+#[derive(Debug)]
+pub struct Framed<T, O, S> {
+    stream: T,
+
+    opening: O,
+    decoder: PacketDecoder,
+    rxseq: u32,
+
+    sealing: S,
+    encoder: PacketEncoder,
+    txseq: u32,
+
+    rekey: Rekey,
+    strict: StrictKex,
+}
+
+impl<T, O, S> Framed<T, O, S> {
+    /// Create a new [`Framed`] transport from a `stream` and the opening and sealing ciphers.
+    pub fn new(stream: T, opening: O, sealing: S) -> Self {
+        Self {
+            stream,
+            opening,
+            decoder: Default::default(),
+            rxseq: 0,
+            sealing,
+            encoder: Default::default(),
+            txseq: 0,
+            rekey: Default::default(),
+            strict: Default::default(),
+        }
+    }
+
+    /// Use the `rekey` accounting for the [`Framed`] transport, in place of the default one.
+    pub fn with_rekey(mut self, rekey: Rekey) -> Self {
+        self.rekey = rekey;
+        self
+    }
+
+    /// Get a reference to the [`Rekey`] accounting of the transport,
+    /// to know whether a new key-exchange should be initiated.
+    pub fn rekey(&self) -> &Rekey {
+        &self.rekey
+    }
+
+    /// Replace the [`OpeningCipher`] used to decrypt the next received packets,
+    /// returning the previous one.
+    pub fn replace_opening(&mut self, cipher: O) -> O {
+        std::mem::replace(&mut self.opening, cipher)
+    }
+
+    /// Replace the [`SealingCipher`] used to encrypt the next sent packets,
+    /// returning the previous one.
+    pub fn replace_sealing(&mut self, cipher: S) -> S {
+        std::mem::replace(&mut self.sealing, cipher)
+    }
+
+    /// The sequence number of the next packet to be received.
+    pub fn rxseq(&self) -> u32 {
+        self.rxseq
+    }
+
+    /// The sequence number of the next packet to be sent.
+    pub fn txseq(&self) -> u32 {
+        self.txseq
+    }
+
+    /// Determine whether strict key-exchange is enabled from the `client`'s and `server`'s
+    /// `SSH_MSG_KEXINIT` messages, see [`StrictKex::negotiate`].
+    pub fn negotiate_strict_kex(
+        &mut self,
+        client: &trans::KexInit<'_>,
+        server: &trans::KexInit<'_>,
+    ) -> Result<bool, Error> {
+        self.strict.negotiate(client, server)
+    }
+
+    /// Get a reference to the [`StrictKex`] checker of the transport.
+    pub fn strict_kex(&self) -> &StrictKex {
+        &self.strict
+    }
+
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &T {
+        &self.stream
+    }
+
+    /// Get a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.stream
+    }
+
+    /// Consume the [`Framed`] transport, returning the underlying stream.
+    ///
+    /// Any received data not yet decoded and any sent data not yet flushed is lost.
+    pub fn into_inner(self) -> T {
+        self.stream
+    }
+}
+
+impl<T: AsyncWrite + Unpin, O, S> Framed<T, O, S> {
+    fn poll_write_buffer(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        while !self.encoder.is_empty() {
+            let written = ready!(Pin::new(&mut self.stream).poll_write(cx, self.encoder.buffer()))?;
+            if written == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+            }
+
+            self.encoder.consume(written);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+// The ciphers are never pinned, only the underlying stream is.
+impl<T: Unpin, O, S> Unpin for Framed<T, O, S> {}
+
+impl<T, O, S> futures::Stream for Framed<T, O, S>
+where
+    T: AsyncRead + Unpin,
+    O: OpeningCipher,
+{
+    type Item = Result<Packet, O::Err>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let buffered = this.decoder.buffered();
+
+            match this.decoder.decode(&mut this.opening, this.rxseq) {
+                Ok(Some(packet)) => {
+                    this.rxseq = this.rxseq.wrapping_add(1);
+                    this.rekey.received(
+                        &packet,
+                        buffered - this.decoder.buffered(),
+                        this.opening.block_size(),
+                    );
+
+                    if let Err(err) = this.strict.received(&packet, &mut this.rxseq) {
+                        return Poll::Ready(Some(Err(violation(err).into())));
+                    }
+
+                    return Poll::Ready(Some(Ok(packet)));
+                }
+                Ok(None) => (),
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+
+            let mut chunk = [0u8; CHUNK_SIZE];
+            let read = match ready!(Pin::new(&mut this.stream).poll_read(cx, &mut chunk)) {
+                Ok(read) => read,
+                Err(err) => return Poll::Ready(Some(Err(err.into()))),
+            };
+
+            if read == 0 {
+                return if this.decoder.is_empty() {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Ready(Some(Err(std::io::Error::from(
+                        std::io::ErrorKind::UnexpectedEof,
+                    )
+                    .into())))
+                };
+            }
+
+            this.decoder.feed(&chunk[..read]);
+        }
+    }
+}
+
+impl<T, O, S> futures::Sink<Packet> for Framed<T, O, S>
+where
+    T: AsyncWrite + Unpin,
+    S: SealingCipher,
+{
+    type Error = S::Err;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        // Apply backpressure when too much data is waiting to be written
+        if this.encoder.len() >= this.sealing.max_packet_size() {
+            ready!(this.poll_write_buffer(cx))?;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Packet) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+
+        let buffered = this.encoder.len();
+        this.encoder
+            .encode(&item.payload, &mut this.sealing, this.txseq)?;
+        this.txseq = this.txseq.wrapping_add(1);
+        this.rekey.sent(
+            &item,
+            this.encoder.len() - buffered,
+            this.sealing.block_size(),
+        );
+
+        this.strict
+            .sent(&item, &mut this.txseq)
+            .map_err(|err| violation(err).into())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_write_buffer(cx))?;
+        ready!(Pin::new(&mut this.stream).poll_flush(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_write_buffer(cx))?;
+        ready!(Pin::new(&mut this.stream).poll_close(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn violation(err: Error) -> binrw::Error {
+    binrw::Error::Custom {
+        pos: 0x0,
+        err: Box::new(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use futures::{SinkExt, TryStreamExt};
+
+    use super::*;
+    use crate::packet::tests::{kexinit, packet, Plain};
+
+    #[async_std::test]
+    async fn it_roundtrips_and_counts() {
+        let payloads = [vec![21], vec![94; 10_000], vec![]];
+        let etm = Plain {
+            etm: true,
+            aead: false,
+        };
+
+        let mut framed = Framed::new(futures::io::Cursor::new(Vec::new()), Plain::default(), etm);
+        for (i, payload) in payloads.iter().enumerate() {
+            framed
+                .feed(Packet {
+                    payload: payload.clone(),
+                })
+                .await
+                .unwrap();
+
+            // Swapping the cipher between two packets is transparent,
+            // the length of the next packets is no longer obfuscated
+            if i == 0 {
+                framed.replace_sealing(Plain::default());
+            }
+        }
+        framed.flush().await.unwrap();
+        assert_eq!(framed.txseq(), payloads.len() as u32);
+
+        let buf = framed.into_inner().into_inner();
+
+        // The next packets are not readable without swapping the cipher as well
+        let mut framed = Framed::new(futures::io::Cursor::new(buf.clone()), etm, etm);
+        assert_eq!(
+            framed.try_next().await.unwrap().unwrap().payload,
+            payloads[0]
+        );
+        assert!(framed.try_next().await.is_err());
+
+        let mut framed = Framed::new(futures::io::Cursor::new(buf), etm, Plain::default());
+        assert_eq!(
+            framed.try_next().await.unwrap().unwrap().payload,
+            payloads[0]
+        );
+
+        framed.replace_opening(Plain::default());
+
+        let rest: Vec<_> = (&mut framed)
+            .map_ok(|packet| packet.payload)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(rest, payloads[1..]);
+        assert_eq!(framed.rxseq(), payloads.len() as u32);
+    }
+
+    #[async_std::test]
+    async fn it_resets_sequence_numbers_when_strict() {
+        let client = kexinit(&["curve25519-sha256"], &[], &[], &[]).with_kex_strict_c();
+        let server = kexinit(&["curve25519-sha256"], &[], &[], &[]).with_kex_strict_s();
+        let cipher = Plain {
+            etm: true,
+            aead: false,
+        };
+
+        let mut framed = Framed::new(futures::io::Cursor::new(Vec::new()), cipher, cipher);
+        framed.send(packet(20)).await.unwrap();
+        assert!(framed.negotiate_strict_kex(&client, &server).unwrap());
+        framed.send(packet(21)).await.unwrap();
+        framed.send(packet(94)).await.unwrap();
+        assert_eq!(framed.txseq(), 1);
+
+        // The length is obfuscated with the sequence number, so it has to be reset on both sides
+        let stream = futures::io::Cursor::new(framed.into_inner().into_inner());
+        let mut framed = Framed::new(stream, cipher, cipher);
+        assert_eq!(framed.try_next().await.unwrap().unwrap().payload, [20]);
+        assert!(framed.negotiate_strict_kex(&client, &server).unwrap());
+        assert_eq!(framed.try_next().await.unwrap().unwrap().payload, [21]);
+        assert_eq!(framed.rxseq(), 0);
+        assert_eq!(framed.try_next().await.unwrap().unwrap().payload, [94]);
+        assert_eq!(framed.rxseq(), 1);
+    }
+}
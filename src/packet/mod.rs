@@ -3,18 +3,36 @@ use binrw::{
     BinRead, BinWrite,
 };
 
+use crate::{Message, MessageContext};
+
 mod cipher;
 pub use cipher::{CipherCore, OpeningCipher, SealingCipher};
 
 mod mac;
 pub use mac::Mac;
 
-/// Maximum size for a SSH packet, coincidentally this is
-/// the maximum size for a TCP packet.
+mod decoder;
+pub use decoder::PacketDecoder;
+
+mod encoder;
+pub use encoder::PacketEncoder;
+
+mod borrow;
+pub(crate) use borrow::read_magic;
+pub use borrow::BorrowRead;
+
+#[cfg(feature = "futures")]
+mod framed;
+#[cfg(feature = "futures")]
+#[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+pub use framed::Framed;
+
+/// Default maximum size for a SSH packet, coincidentally this is
+/// the maximum size for a TCP packet, see [`CipherCore::max_packet_size`].
 pub const PACKET_MAX_SIZE: usize = u16::MAX as usize;
 
-/// Minimum size for a SSH packet, coincidentally this is
-/// the largest block cipher's block-size.
+/// Default minimum size for a SSH packet, coincidentally this is
+/// the largest block cipher's block-size, see [`CipherCore::min_packet_size`].
 pub const PACKET_MIN_SIZE: usize = 16;
 
 /// A SSH 2.0 binary packet representation.
@@ -29,10 +47,65 @@ pub struct Packet {
 
 impl Packet {
     /// Try to deserialize the [`Packet`] into `T`.
+    ///
+    /// Some message numbers are shared by several messages, prefer [`Packet::message`]
+    /// when the type of the message is not known in advance.
     pub fn to<T: for<'a> BinRead<Args<'a> = ()> + ReadEndian>(&self) -> Result<T, binrw::Error> {
         T::read(&mut std::io::Cursor::new(&self.payload))
     }
 
+    /// Try to deserialize the [`Packet`] into `T`, borrowing the `string`s
+    /// from the payload instead of copying them, see [`BorrowRead`].
+    pub fn borrow_as<'b, T: BorrowRead<'b>>(&'b self) -> Result<T, binrw::Error> {
+        T::borrow_read(&mut std::io::Cursor::new(&self.payload[..]))
+    }
+
+    /// Try to deserialize the [`Packet`] into any known [`Message`],
+    /// dispatching on it's _message number_ in the provided `context`.
+    pub fn message(&self, context: &MessageContext) -> Result<Message<'static>, binrw::Error> {
+        Message::read_args(&mut std::io::Cursor::new(&self.payload), context)
+    }
+
+    /// Read a [`Packet`] from the provided synchronous `reader`.
+    pub fn from_sync_reader<R, C>(reader: &mut R, cipher: &mut C, seq: u32) -> Result<Self, C::Err>
+    where
+        R: std::io::Read,
+        C: OpeningCipher,
+    {
+        let mut buf = vec![0; cipher.block_size()];
+        reader.read_exact(&mut buf[..])?;
+
+        let len = Self::open_head(cipher, &mut buf, seq)?;
+
+        // Read the rest of the data from the reader
+        buf.resize(std::mem::size_of_val(&len) + len as usize, 0);
+        reader.read_exact(&mut buf[cipher.block_size()..])?;
+
+        let mut mac = vec![0; cipher.mac().size()];
+        reader.read_exact(&mut mac[..])?;
+
+        Self::open_body(cipher, buf, mac, seq)
+    }
+
+    /// Write the [`Packet`] to the provided synchronous `writer`.
+    pub fn to_sync_writer<W, C>(
+        &self,
+        writer: &mut W,
+        cipher: &mut C,
+        seq: u32,
+    ) -> Result<(), C::Err>
+    where
+        W: std::io::Write,
+        C: SealingCipher,
+    {
+        let mut buf = Vec::new();
+        Self::encode(&self.payload, cipher, seq, &mut buf)?;
+
+        writer.write_all(&buf)?;
+
+        Ok(())
+    }
+
     #[cfg(feature = "futures")]
     #[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
     /// Read a [`Packet`] from the provided asynchronous `reader`.
@@ -46,31 +119,162 @@ impl Packet {
         let mut buf = vec![0; cipher.block_size()];
         reader.read_exact(&mut buf[..]).await?;
 
-        if !cipher.mac().etm() {
+        let len = Self::open_head(cipher, &mut buf, seq)?;
+
+        // Read the rest of the data from the reader
+        buf.resize(std::mem::size_of_val(&len) + len as usize, 0);
+        reader.read_exact(&mut buf[cipher.block_size()..]).await?;
+
+        let mut mac = vec![0; cipher.mac().size()];
+        reader.read_exact(&mut mac[..]).await?;
+
+        Self::open_body(cipher, buf, mac, seq)
+    }
+
+    #[cfg(feature = "futures")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+    /// Write the [`Packet`] to the provided asynchronous `writer`.
+    pub async fn to_writer<W, C>(
+        &self,
+        writer: &mut W,
+        cipher: &mut C,
+        seq: u32,
+    ) -> Result<(), C::Err>
+    where
+        W: futures::io::AsyncWrite + Unpin,
+        C: SealingCipher,
+    {
+        use futures::AsyncWriteExt;
+
+        let mut buf = Vec::new();
+        Self::encode(&self.payload, cipher, seq, &mut buf)?;
+
+        writer.write_all(&buf).await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Read a [`Packet`] from the provided asynchronous `tokio` `reader`.
+    pub async fn from_tokio_reader<R, C>(
+        reader: &mut R,
+        cipher: &mut C,
+        seq: u32,
+    ) -> Result<Self, C::Err>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        C: OpeningCipher,
+    {
+        use tokio::io::AsyncReadExt;
+
+        let mut buf = vec![0; cipher.block_size()];
+        reader.read_exact(&mut buf[..]).await?;
+
+        let len = Self::open_head(cipher, &mut buf, seq)?;
+
+        // Read the rest of the data from the reader
+        buf.resize(std::mem::size_of_val(&len) + len as usize, 0);
+        reader.read_exact(&mut buf[cipher.block_size()..]).await?;
+
+        let mut mac = vec![0; cipher.mac().size()];
+        reader.read_exact(&mut mac[..]).await?;
+
+        Self::open_body(cipher, buf, mac, seq)
+    }
+
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    /// Write the [`Packet`] to the provided asynchronous `tokio` `writer`.
+    pub async fn to_tokio_writer<W, C>(
+        &self,
+        writer: &mut W,
+        cipher: &mut C,
+        seq: u32,
+    ) -> Result<(), C::Err>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+        C: SealingCipher,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let mut buf = Vec::new();
+        Self::encode(&self.payload, cipher, seq, &mut buf)?;
+
+        writer.write_all(&buf).await?;
+
+        Ok(())
+    }
+
+    /// Open the first block of a packet, of the size of the cipher's block,
+    /// and extract the length of the rest of the packet from it.
+    fn open_head<C: OpeningCipher>(
+        cipher: &mut C,
+        buf: &mut [u8],
+        seq: u32,
+    ) -> Result<u32, C::Err> {
+        let len = if cipher.mac().etm() || cipher.aead() {
+            let len = buf[..4]
+                .try_into()
+                .expect("The buffer of size 4 is not of size 4");
+
+            cipher.decrypt_length(len, seq)?
+        } else {
             cipher.decrypt(&mut buf[..])?;
+
+            u32::from_be_bytes(
+                buf[..4]
+                    .try_into()
+                    .expect("The buffer of size 4 is not of size 4"),
+            )
+        };
+
+        let max = cipher.max_packet_size();
+        if len as usize > max {
+            return Err(binrw::Error::Custom {
+                pos: 0x0,
+                err: Box::new(format!("Packet size too large, {len} > {max}")),
+            })?;
         }
 
-        let len = u32::from_be_bytes(
-            buf[..4]
-                .try_into()
-                .expect("The buffer of size 4 is not of size 4"),
-        );
+        // The packet length is not part of the padded data for these ciphers
+        let min = if cipher.mac().etm() || cipher.aead() {
+            cipher
+                .min_packet_size()
+                .saturating_sub(std::mem::size_of_val(&len))
+        } else {
+            cipher.min_packet_size()
+        };
+        if std::mem::size_of_val(&len) + (len as usize) < min {
+            return Err(binrw::Error::Custom {
+                pos: 0x0,
+                err: Box::new(format!("Packet size too small ({len})")),
+            })?;
+        }
 
-        if len as usize > PACKET_MAX_SIZE {
+        if std::mem::size_of_val(&len) + (len as usize) < buf.len() {
             return Err(binrw::Error::Custom {
                 pos: 0x0,
-                err: Box::new(format!("Packet size too large, {len} > {PACKET_MAX_SIZE}")),
+                err: Box::new(format!("Packet size too small ({len})")),
             })?;
         }
 
-        // Read the rest of the data from the reader
-        buf.resize(std::mem::size_of_val(&len) + len as usize, 0);
-        reader.read_exact(&mut buf[cipher.block_size()..]).await?;
+        Ok(len)
+    }
 
-        let mut mac = vec![0; cipher.mac().size()];
-        reader.read_exact(&mut mac[..]).await?;
+    /// Open the whole packet from it's opened first block followed by the rest
+    /// of the data, and it's _Message Authentication Code_.
+    fn open_body<C: OpeningCipher>(
+        cipher: &mut C,
+        mut buf: Vec<u8>,
+        mac: Vec<u8>,
+        seq: u32,
+    ) -> Result<Self, C::Err> {
+        let len = buf.len() - std::mem::size_of::<u32>();
 
-        if cipher.mac().etm() {
+        if cipher.aead() {
+            cipher.open_aead(&mut buf, mac, seq)?;
+        } else if cipher.mac().etm() {
             cipher.open(&buf, mac, seq)?;
             cipher.decrypt(&mut buf[4..])?;
         } else {
@@ -84,14 +288,14 @@ impl Packet {
                 err: Box::new(format!("Packet size too small ({len})")),
             })?;
 
-        if *padlen as usize > len as usize - 1 {
+        if *padlen as usize > len - 1 {
             return Err(binrw::Error::Custom {
                 pos: 0x4,
                 err: Box::new(format!("Padding size too large, {padlen} > {} - 1", len)),
             })?;
         }
 
-        let mut payload = vec![0; len as usize - *padlen as usize - std::mem::size_of_val(padlen)];
+        let mut payload = vec![0; len - *padlen as usize - std::mem::size_of_val(padlen)];
         std::io::Read::read_exact(&mut decrypted, &mut payload[..])?;
 
         let payload = cipher.decompress(payload)?;
@@ -99,43 +303,82 @@ impl Packet {
         Ok(Self { payload })
     }
 
-    #[cfg(feature = "futures")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
-    /// Write the [`Packet`] to the provided asynchronous `writer`.
-    pub async fn to_writer<W, C>(
-        &self,
-        writer: &mut W,
+    /// Seal the `payload` into its encrypted form, followed by its
+    /// _Message Authentication Code_, by appending them to the `buf`.
+    ///
+    /// If sealing fails, the `buf` is left as it was before the call, but the `cipher`'s
+    /// state, such as its compression stream, may have advanced and must be considered poisoned.
+    fn encode<C: SealingCipher>(
+        payload: &[u8],
         cipher: &mut C,
         seq: u32,
-    ) -> Result<(), C::Err>
-    where
-        W: futures::io::AsyncWrite + Unpin,
-        C: SealingCipher,
-    {
-        use futures::AsyncWriteExt;
+        buf: &mut Vec<u8>,
+    ) -> Result<(), C::Err> {
+        let start = buf.len();
 
-        let compressed = cipher.compress(&self.payload)?;
+        let result = Self::encode_at(payload, cipher, seq, buf, start);
+        if result.is_err() {
+            buf.truncate(start);
+        }
 
-        let padding = cipher.padding(compressed.len());
-        let buf = cipher.pad(compressed, padding)?;
-        let mut buf = [(buf.len() as u32).to_be_bytes().to_vec(), buf].concat();
+        result
+    }
 
-        let (buf, mac) = if cipher.mac().etm() {
-            cipher.encrypt(&mut buf[4..])?;
-            let mac = cipher.seal(&buf, seq)?;
+    fn encode_at<C: SealingCipher>(
+        payload: &[u8],
+        cipher: &mut C,
+        seq: u32,
+        buf: &mut Vec<u8>,
+        start: usize,
+    ) -> Result<(), C::Err> {
+        // Leave room for the packet length and the padding length, filled once known
+        buf.extend_from_slice(&[0; 5]);
+        cipher.compress_into(payload, buf)?;
+
+        let padding = cipher.padding(buf.len() - start - 5);
+        let end = buf.len() + padding as usize;
+        buf.resize(end, 0);
+        cipher.fill_padding(&mut buf[end - padding as usize..])?;
+
+        let len = end - start - std::mem::size_of::<u32>();
+        let max = cipher.max_packet_size();
+        if len > max {
+            return Err(binrw::Error::Custom {
+                pos: 0x0,
+                err: Box::new(format!("Packet size too large, {len} > {max}")),
+            })?;
+        }
 
-            (buf, mac)
-        } else {
-            let mac = cipher.seal(&buf, seq)?;
-            cipher.encrypt(&mut buf[..])?;
+        // The padding is not always large enough to reach the minimum size
+        let min = cipher.min_packet_size();
+        if end - start < min {
+            return Err(binrw::Error::Custom {
+                pos: 0x0,
+                err: Box::new(format!("Packet size too small, {} < {min}", end - start)),
+            })?;
+        }
 
-            (buf, mac)
+        let len = len as u32;
+        let len = if cipher.mac().etm() || cipher.aead() {
+            cipher.encrypt_length(len, seq)?
+        } else {
+            len.to_be_bytes()
         };
+        buf[start..start + 4].copy_from_slice(&len);
+        buf[start + 4] = padding;
 
-        writer.write_all(&buf).await?;
-        writer.write_all(&mac).await?;
+        buf.resize(end + cipher.mac().size(), 0);
+        let (packet, mac) = buf[start..].split_at_mut(end - start);
 
-        Ok(())
+        if cipher.aead() {
+            cipher.seal_aead_into(packet, seq, mac)
+        } else if cipher.mac().etm() {
+            cipher.encrypt(&mut packet[4..])?;
+            cipher.seal_into(packet, seq, mac)
+        } else {
+            cipher.seal_into(packet, seq, mac)?;
+            cipher.encrypt(packet)
+        }
     }
 }
 
@@ -162,3 +405,241 @@ impl<T: for<'a> BinWrite<Args<'a> = ()> + WriteEndian> IntoPacket for &T {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{arch, trans};
+
+    /// A [`Packet`] only made of the _message number_ `number`.
+    pub(crate) fn packet(number: u8) -> Packet {
+        Packet {
+            payload: vec![number],
+        }
+    }
+
+    /// A `SSH_MSG_KEXINIT` message with the provided algorithms, in both directions.
+    pub(crate) fn kexinit(
+        kex: &[&str],
+        host_key: &[&str],
+        encryption: &[&str],
+        mac: &[&str],
+    ) -> trans::KexInit<'static> {
+        trans::KexInit {
+            cookie: Default::default(),
+            kex_algorithms: kex.iter().collect(),
+            server_host_key_algorithms: host_key.iter().collect(),
+            encryption_algorithms_client_to_server: encryption.iter().collect(),
+            encryption_algorithms_server_to_client: encryption.iter().collect(),
+            mac_algorithms_client_to_server: mac.iter().collect(),
+            mac_algorithms_server_to_client: mac.iter().collect(),
+            compression_algorithms_client_to_server: ["none"].into_iter().collect(),
+            compression_algorithms_server_to_client: ["none"].into_iter().collect(),
+            languages_client_to_server: Default::default(),
+            languages_server_to_client: Default::default(),
+            first_kex_packet_follows: Default::default(),
+        }
+    }
+
+    /// A cipher which doesn't encrypt nor authenticate anything,
+    /// except for the unencrypted length, obfuscated with the sequence number.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub(crate) struct Plain {
+        pub(crate) etm: bool,
+        pub(crate) aead: bool,
+    }
+
+    impl Mac for Plain {
+        fn size(&self) -> usize {
+            0
+        }
+
+        fn etm(&self) -> bool {
+            self.etm
+        }
+    }
+
+    impl CipherCore for Plain {
+        type Err = binrw::Error;
+        type Mac = Self;
+
+        fn mac(&self) -> &Self::Mac {
+            self
+        }
+
+        fn block_size(&self) -> usize {
+            8
+        }
+
+        fn aead(&self) -> bool {
+            self.aead
+        }
+    }
+
+    impl OpeningCipher for Plain {
+        fn decrypt<B: AsMut<[u8]>>(&mut self, _buf: B) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        fn open<B: AsRef<[u8]>>(
+            &mut self,
+            _buf: B,
+            _mac: Vec<u8>,
+            _seq: u32,
+        ) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        fn decompress(&mut self, buf: Vec<u8>) -> Result<Vec<u8>, Self::Err> {
+            Ok(buf)
+        }
+
+        fn decrypt_length(&mut self, buf: [u8; 4], seq: u32) -> Result<u32, Self::Err> {
+            Ok(u32::from_be_bytes(buf) ^ !seq)
+        }
+
+        fn open_aead<B: AsMut<[u8]>>(
+            &mut self,
+            _buf: B,
+            _tag: Vec<u8>,
+            _seq: u32,
+        ) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    impl SealingCipher for Plain {
+        fn compress<B: AsRef<[u8]>>(&mut self, buf: B) -> Result<Vec<u8>, Self::Err> {
+            Ok(buf.as_ref().to_vec())
+        }
+
+        fn pad(&mut self, buf: Vec<u8>, padding: u8) -> Result<Vec<u8>, Self::Err> {
+            Ok([&[padding][..], &buf, &vec![0; padding as usize]].concat())
+        }
+
+        fn encrypt<B: AsMut<[u8]>>(&mut self, _buf: B) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        fn seal<B: AsRef<[u8]>>(&mut self, _buf: B, _seq: u32) -> Result<Vec<u8>, Self::Err> {
+            Ok(Vec::new())
+        }
+
+        fn encrypt_length(&mut self, len: u32, seq: u32) -> Result<[u8; 4], Self::Err> {
+            Ok((len ^ !seq).to_be_bytes())
+        }
+
+        fn seal_aead<B: AsMut<[u8]>>(&mut self, _buf: B, _seq: u32) -> Result<Vec<u8>, Self::Err> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[rstest]
+    #[case(Plain { etm: false, aead: false }, vec![])]
+    #[case(Plain { etm: false, aead: false }, vec![2, 0, 0, 0, 0])]
+    #[case(Plain { etm: true, aead: false }, vec![94; 1024])]
+    #[case(Plain { etm: false, aead: true }, vec![94; 1024])]
+    fn it_roundtrips_sync(#[case] mut cipher: Plain, #[case] payload: Vec<u8>) {
+        let mut buf = Vec::new();
+        Packet {
+            payload: payload.clone(),
+        }
+        .to_sync_writer(&mut buf, &mut cipher, 0)
+        .unwrap();
+
+        let mut reader = &buf[..];
+        let packet = Packet::from_sync_reader(&mut reader, &mut cipher, 0).unwrap();
+
+        assert_eq!(packet.payload, payload);
+        assert!(reader.is_empty());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[rstest]
+    #[case(Plain { etm: false, aead: false }, vec![2, 0, 0, 0, 0])]
+    #[case(Plain { etm: true, aead: false }, vec![94; 1024])]
+    #[tokio::test]
+    async fn it_roundtrips_tokio(#[case] mut cipher: Plain, #[case] payload: Vec<u8>) {
+        let mut buf = Vec::new();
+        Packet {
+            payload: payload.clone(),
+        }
+        .to_tokio_writer(&mut buf, &mut cipher, 0)
+        .await
+        .unwrap();
+
+        let mut reader = &buf[..];
+        let packet = Packet::from_tokio_reader(&mut reader, &mut cipher, 0)
+            .await
+            .unwrap();
+
+        assert_eq!(packet.payload, payload);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn it_borrows_payload() {
+        let packet = (&crate::connect::ChannelData {
+            recipient_channel: 42,
+            data: arch::Bytes::owned(vec![0x42; 1024]),
+        })
+            .into_packet();
+
+        let message: crate::connect::ChannelData = packet.borrow_as().unwrap();
+        assert_eq!(message.recipient_channel, 42);
+        assert_eq!(&*message.data, &packet.payload[9..]);
+        assert!(std::ptr::eq(&*message.data, &packet.payload[9..]));
+
+        assert!(matches!(
+            packet.borrow_as::<crate::connect::ChannelExtendedData>(),
+            Err(binrw::Error::BadMagic { pos: 0, .. })
+        ));
+    }
+
+    #[rstest]
+    #[case(0, b"")]
+    #[case(42, b"data")]
+    #[case(u32::MAX, &[0xff; 1024])]
+    fn it_borrows_like_reading(#[case] recipient_channel: u32, #[case] data: &[u8]) {
+        use crate::connect::{ChannelData, ChannelExtendedData};
+
+        let packet = (&ChannelData {
+            recipient_channel,
+            data: arch::Bytes::borrowed(data),
+        })
+            .into_packet();
+        let borrowed: ChannelData = packet.borrow_as().unwrap();
+        let read: ChannelData = packet.to().unwrap();
+        assert_eq!(borrowed.recipient_channel, read.recipient_channel);
+        assert_eq!(borrowed.data, read.data);
+
+        let packet = (&ChannelExtendedData {
+            recipient_channel,
+            data_type: std::num::NonZeroU32::MIN,
+            data: arch::Bytes::borrowed(data),
+        })
+            .into_packet();
+        let borrowed: ChannelExtendedData = packet.borrow_as().unwrap();
+        let read: ChannelExtendedData = packet.to().unwrap();
+        assert_eq!(borrowed.recipient_channel, read.recipient_channel);
+        assert_eq!(borrowed.data_type, read.data_type);
+        assert_eq!(borrowed.data, read.data);
+
+        // Both reject a truncated payload alike
+        let truncated = Packet {
+            payload: packet.payload[..packet.payload.len() - 1].to_vec(),
+        };
+        assert!(truncated.borrow_as::<ChannelExtendedData>().is_err());
+        assert!(truncated.to::<ChannelExtendedData>().is_err());
+    }
+
+    #[test]
+    fn it_rejects_oversized() {
+        let buf = [&u32::MAX.to_be_bytes()[..], &[0; 12]].concat();
+
+        Packet::from_sync_reader(&mut &buf[..], &mut Plain::default(), 0).unwrap_err();
+    }
+}
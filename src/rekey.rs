@@ -0,0 +1,238 @@
+use std::time::{Duration, Instant};
+
+use crate::Packet;
+
+/// The default amount of bytes after which to rekey, as recommended by RFC 4253.
+const DEFAULT_BYTES: u64 = 1 << 30;
+
+/// The default amount of packets after which to rekey, well before the sequence numbers wrap.
+const DEFAULT_PACKETS: u64 = 1 << 31;
+
+/// The default interval after which to rekey, as recommended by RFC 4253.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy)]
+struct Direction {
+    /// Whether the `SSH_MSG_NEWKEYS` message has been processed in this direction
+    /// since the last `SSH_MSG_KEXINIT` message.
+    keyed: bool,
+
+    bytes: u64,
+    blocks: u64,
+    packets: u64,
+    block_size: usize,
+    since: Instant,
+}
+
+impl Default for Direction {
+    fn default() -> Self {
+        Self {
+            keyed: false,
+            bytes: 0,
+            blocks: 0,
+            packets: 0,
+            block_size: 0,
+            since: Instant::now(),
+        }
+    }
+}
+
+impl Direction {
+    fn process(&mut self, number: u8, size: usize, block_size: usize) {
+        if number == 21 {
+            *self = Self {
+                keyed: true,
+                ..Default::default()
+            };
+        } else {
+            self.bytes = self.bytes.saturating_add(size as u64);
+            self.blocks = self
+                .blocks
+                .saturating_add(size.div_ceil(block_size.max(1)) as u64);
+            self.packets = self.packets.saturating_add(1);
+            self.block_size = block_size;
+        }
+    }
+
+    /// The maximum amount of blocks to be encrypted with the same key, `2^(L/4)` for
+    /// a block size of `L` bits as recommended by RFC 4344, or 1 GiB worth of blocks
+    /// for block sizes under 16 bytes, as OpenSSH does.
+    fn max_blocks(&self) -> u64 {
+        if self.block_size >= 16 {
+            1u64.checked_shl((self.block_size as u32).saturating_mul(2))
+                .unwrap_or(u64::MAX)
+        } else {
+            (1 << 30) / self.block_size.max(1) as u64
+        }
+    }
+}
+
+/// An accounting of the traffic in both directions of the transport, reporting
+/// when a new key-exchange should be initiated by sending a `SSH_MSG_KEXINIT` message.
+///
+/// The accounting is fed with every [`Packet`] received and sent, along with its size
+/// on the wire and the block size of the cipher in use, and is reset for a direction
+/// once the `SSH_MSG_NEWKEYS` message has been processed in that direction.
+///
+/// A rekey is reported as needed when any direction exceeds either the limit of bytes
+/// (1 GiB by default), of packets (2^31 by default), of blocks (2^(L/4) for a block size
+/// of `L` bits, or 1 GiB worth of blocks under 16 bytes), or of time (an hour by default).
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-9>
+/// and <https://datatracker.ietf.org/doc/html/rfc4344#section-3.2>.
+#[derive(Debug, Clone)]
+pub struct Rekey {
+    bytes: u64,
+    packets: u64,
+    interval: Option<Duration>,
+
+    rx: Direction,
+    tx: Direction,
+}
+
+impl Default for Rekey {
+    fn default() -> Self {
+        Self {
+            bytes: DEFAULT_BYTES,
+            packets: DEFAULT_PACKETS,
+            interval: Some(DEFAULT_INTERVAL),
+            rx: Default::default(),
+            tx: Default::default(),
+        }
+    }
+}
+
+impl Rekey {
+    /// Create a new [`Rekey`] accounting with the default limits,
+    /// before the initial key-exchange has been performed.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the amount of bytes in a single direction after which to rekey.
+    pub fn with_bytes(mut self, bytes: u64) -> Self {
+        self.bytes = bytes;
+        self
+    }
+
+    /// Set the amount of packets in a single direction after which to rekey.
+    pub fn with_packets(mut self, packets: u64) -> Self {
+        self.packets = packets;
+        self
+    }
+
+    /// Set the interval after which to rekey, or [`None`] to disable it.
+    pub fn with_interval(mut self, interval: Option<Duration>) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Account for the received `packet`, of `size` bytes on the wire
+    /// and opened by a cipher of `block_size` bytes.
+    pub fn received(&mut self, packet: &Packet, size: usize, block_size: usize) {
+        self.process(packet, size, block_size, true);
+    }
+
+    /// Account for the sent `packet`, of `size` bytes on the wire
+    /// and sealed by a cipher of `block_size` bytes.
+    pub fn sent(&mut self, packet: &Packet, size: usize, block_size: usize) {
+        self.process(packet, size, block_size, false);
+    }
+
+    /// Whether a new key-exchange should be initiated, which is never the case
+    /// while a key-exchange is already in progress.
+    pub fn is_needed(&self) -> bool {
+        self.rx.keyed && self.tx.keyed && (self.exceeds(&self.rx) || self.exceeds(&self.tx))
+    }
+
+    fn process(&mut self, packet: &Packet, size: usize, block_size: usize, received: bool) {
+        let number = packet.payload.first().copied().unwrap_or_default();
+
+        // A key-exchange is in progress until both directions are re-keyed
+        if number == 20 {
+            self.rx.keyed = false;
+            self.tx.keyed = false;
+        }
+
+        let direction = if received { &mut self.rx } else { &mut self.tx };
+        direction.process(number, size, block_size);
+    }
+
+    fn exceeds(&self, direction: &Direction) -> bool {
+        direction.bytes >= self.bytes
+            || direction.packets >= self.packets
+            || direction.blocks >= direction.max_blocks()
+            || self
+                .interval
+                .is_some_and(|interval| direction.since.elapsed() >= interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::packet::tests::packet;
+
+    fn keyed(mut rekey: Rekey) -> Rekey {
+        rekey.sent(&packet(20), 64, 8);
+        rekey.received(&packet(20), 64, 8);
+        rekey.sent(&packet(21), 16, 8);
+        rekey.received(&packet(21), 16, 8);
+
+        rekey
+    }
+
+    #[rstest]
+    #[case(Rekey::new().with_bytes(1024), 1024, 16, 1)]
+    #[case(Rekey::new().with_packets(10), 32, 16, 10)]
+    #[case(Rekey::new(), 1 << 20, 8, 1 << 10)]
+    #[case(Rekey::new().with_bytes(u64::MAX), 1 << 20, 8, 1 << 10)]
+    #[case(Rekey::new().with_bytes(u64::MAX), 1 << 20, 16, 1 << 16)]
+    fn it_reports_limits(
+        #[case] rekey: Rekey,
+        #[case] size: usize,
+        #[case] block_size: usize,
+        #[case] count: usize,
+    ) {
+        let mut rekey = keyed(rekey);
+
+        for _ in 0..count - 1 {
+            rekey.sent(&packet(94), size, block_size);
+        }
+        assert!(!rekey.is_needed());
+
+        rekey.sent(&packet(94), size, block_size);
+        assert!(rekey.is_needed());
+
+        // No rekey is reported while one is in progress, and the counters reset afterwards
+        rekey.sent(&packet(20), 64, block_size);
+        assert!(!rekey.is_needed());
+
+        let rekey = keyed(rekey);
+        assert!(!rekey.is_needed());
+    }
+
+    #[test]
+    fn it_reports_interval() {
+        let rekey = keyed(Rekey::new().with_interval(Some(Duration::ZERO)));
+        assert!(rekey.is_needed());
+
+        let rekey = keyed(Rekey::new().with_interval(None));
+        assert!(!rekey.is_needed());
+    }
+
+    #[test]
+    fn it_waits_initial_kex() {
+        let mut rekey = Rekey::new().with_bytes(0);
+        assert!(!rekey.is_needed());
+
+        rekey.sent(&packet(21), 16, 8);
+        assert!(!rekey.is_needed());
+
+        rekey.received(&packet(21), 16, 8);
+        assert!(rekey.is_needed());
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{trans, Error, Packet};
+
+/// Whether the _message number_ is allowed while the initial key-exchange is in progress.
+fn is_kex(number: u8) -> bool {
+    matches!(number, 1 | 20 | 21 | 30..=49)
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Direction {
+    /// Whether the `SSH_MSG_NEWKEYS` message has been processed in this direction.
+    keyed: bool,
+}
+
+impl Direction {
+    fn process(&mut self, number: u8, strict: bool, seq: &mut u32) -> Result<(), Error> {
+        if strict && !self.keyed && *seq == 0 {
+            return Err(Error::StrictKex(
+                "the sequence number wrapped during the initial key-exchange",
+            ));
+        }
+
+        if number == 21 {
+            self.keyed = true;
+
+            if strict {
+                *seq = 0;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A checker enforcing the _strict key-exchange_ extension of OpenSSH,
+/// mitigating prefix truncation attacks such as _Terrapin_.
+///
+/// The checker is fed with every [`Packet`] received and sent, along with the sequence number
+/// of the _next_ packet in that direction, which gets reset on `SSH_MSG_NEWKEYS` when strict
+/// key-exchange has been negotiated, as done by the `Framed` transport.
+///
+/// see <https://github.com/openssh/openssh-portable/blob/master/PROTOCOL>, section 1.9.
+#[derive(Debug, Default, Clone)]
+pub struct StrictKex {
+    strict: Option<bool>,
+    unexpected: bool,
+
+    rx: Direction,
+    tx: Direction,
+}
+
+impl StrictKex {
+    /// Create a new [`StrictKex`] checker, before any packet has been exchanged.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Whether strict key-exchange has been negotiated, or [`None`] if not yet known.
+    pub fn is_strict(&self) -> Option<bool> {
+        self.strict
+    }
+
+    /// Determine whether strict key-exchange is enabled from the `client`'s and `server`'s
+    /// `SSH_MSG_KEXINIT` messages, which only has effect for the initial key-exchange.
+    pub fn negotiate(
+        &mut self,
+        client: &trans::KexInit<'_>,
+        server: &trans::KexInit<'_>,
+    ) -> Result<bool, Error> {
+        let strict = *self
+            .strict
+            .get_or_insert_with(|| client.kex_strict_c() && server.kex_strict_s());
+
+        if strict && self.unexpected {
+            return Err(Error::StrictKex(
+                "the peer's `SSH_MSG_KEXINIT` was not the first received message",
+            ));
+        }
+
+        Ok(strict)
+    }
+
+    /// Check the received `packet`, with `seq` being the sequence number of the next received packet.
+    pub fn received(&mut self, packet: &Packet, seq: &mut u32) -> Result<(), Error> {
+        let number = packet.payload.first().copied().unwrap_or_default();
+
+        if !self.rx.keyed && !is_kex(number) {
+            match self.strict {
+                Some(true) => {
+                    return Err(Error::StrictKex(
+                        "unexpected message during the initial key-exchange",
+                    ))
+                }
+                Some(false) => (),
+                // Strictness is not known yet, defer the error to the negotiation
+                None => self.unexpected = true,
+            }
+        }
+
+        self.rx
+            .process(number, self.strict.unwrap_or_default(), seq)
+    }
+
+    /// Check the sent `packet`, with `seq` being the sequence number of the next sent packet.
+    pub fn sent(&mut self, packet: &Packet, seq: &mut u32) -> Result<(), Error> {
+        let number = packet.payload.first().copied().unwrap_or_default();
+
+        self.tx
+            .process(number, self.strict.unwrap_or_default(), seq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rstest::rstest;
+
+    use super::*;
+    use crate::packet::tests::{kexinit, packet};
+
+    #[rstest]
+    #[case(true, true, true)]
+    #[case(true, false, false)]
+    #[case(false, true, false)]
+    fn it_resets_sequence_numbers(
+        #[case] client: bool,
+        #[case] server: bool,
+        #[case] strict: bool,
+    ) {
+        let mut client_kexinit = kexinit(&["curve25519-sha256"], &[], &[], &[]);
+        if client {
+            client_kexinit = client_kexinit.with_kex_strict_c();
+        }
+        let mut server_kexinit = kexinit(&["curve25519-sha256"], &[], &[], &[]);
+        if server {
+            server_kexinit = server_kexinit.with_kex_strict_s();
+        }
+
+        let mut checker = StrictKex::new();
+        checker.sent(&packet(20), &mut 1).unwrap();
+        checker.received(&packet(20), &mut 1).unwrap();
+        assert_eq!(
+            checker.negotiate(&client_kexinit, &server_kexinit).unwrap(),
+            strict
+        );
+
+        let (mut rxseq, mut txseq) = (3, 4);
+        checker.received(&packet(21), &mut rxseq).unwrap();
+        checker.sent(&packet(21), &mut txseq).unwrap();
+        assert_eq!((rxseq, txseq), if strict { (0, 0) } else { (3, 4) });
+
+        // Messages are no longer restricted after the initial key-exchange
+        checker.received(&packet(2), &mut 1).unwrap();
+    }
+
+    #[test]
+    fn it_rejects_unexpected_messages() {
+        let mut checker = StrictKex::new();
+        checker.received(&packet(20), &mut 1).unwrap();
+        checker
+            .negotiate(
+                &kexinit(&[], &[], &[], &[]).with_kex_strict_c(),
+                &kexinit(&[], &[], &[], &[]).with_kex_strict_s(),
+            )
+            .unwrap();
+
+        assert!(matches!(
+            checker.received(&packet(2), &mut 2),
+            Err(Error::StrictKex(_))
+        ));
+    }
+
+    #[rstest]
+    #[case(true)]
+    #[case(false)]
+    fn it_rejects_messages_before_kexinit(#[case] strict: bool) {
+        let mut checker = StrictKex::new();
+        checker.received(&packet(2), &mut 1).unwrap();
+        checker.received(&packet(20), &mut 2).unwrap();
+
+        let (client, server) = if strict {
+            (
+                kexinit(&[], &[], &[], &[]).with_kex_strict_c(),
+                kexinit(&[], &[], &[], &[]).with_kex_strict_s(),
+            )
+        } else {
+            (kexinit(&[], &[], &[], &[]), kexinit(&[], &[], &[], &[]))
+        };
+        assert_eq!(checker.negotiate(&client, &server).is_err(), strict);
+    }
+}
@@ -0,0 +1,303 @@
+use rand_core::CryptoRngCore;
+
+use super::KexInit;
+use crate::{arch, Error};
+
+/// A builder for the `SSH_MSG_KEXINIT` message, starting either
+/// empty or from one of the curated algorithm profiles.
+///
+/// The algorithms are listed by order of preference, and are validated
+/// against the naming rules of the protocol when building the message.
+#[derive(Debug, Default, Clone)]
+pub struct KexInitBuilder {
+    kex_algorithms: Vec<String>,
+    server_host_key_algorithms: Vec<String>,
+    encryption_algorithms_client_to_server: Vec<String>,
+    encryption_algorithms_server_to_client: Vec<String>,
+    mac_algorithms_client_to_server: Vec<String>,
+    mac_algorithms_server_to_client: Vec<String>,
+    compression_algorithms_client_to_server: Vec<String>,
+    compression_algorithms_server_to_client: Vec<String>,
+    first_kex_packet_follows: bool,
+}
+
+macro_rules! setters {
+    ($($(#[$meta:meta])* $field:ident),+ $(,)?) => {
+        $(
+            $(#[$meta])*
+            pub fn $field<I: IntoIterator<Item = impl Into<String>>>(mut self, names: I) -> Self {
+                self.$field = names.into_iter().map(Into::into).collect();
+                self
+            }
+        )+
+    };
+}
+
+impl KexInitBuilder {
+    /// Create an empty [`KexInitBuilder`], with no algorithms.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// A profile restricted to modern algorithms, including
+    /// the hybrid post-quantum key-exchange methods.
+    pub fn modern() -> Self {
+        Self::new()
+            .kex_algorithms([
+                "mlkem768x25519-sha256",
+                "sntrup761x25519-sha512@openssh.com",
+                "curve25519-sha256",
+                "curve25519-sha256@libssh.org",
+            ])
+            .server_host_key_algorithms([
+                "ssh-ed25519",
+                "ecdsa-sha2-nistp256",
+                "rsa-sha2-512",
+                "rsa-sha2-256",
+            ])
+            .encryption_algorithms([
+                "chacha20-poly1305@openssh.com",
+                "aes256-gcm@openssh.com",
+                "aes128-gcm@openssh.com",
+                "aes256-ctr",
+                "aes128-ctr",
+            ])
+            .mac_algorithms([
+                "hmac-sha2-256-etm@openssh.com",
+                "hmac-sha2-512-etm@openssh.com",
+            ])
+            .compression_algorithms(["none"])
+    }
+
+    /// A profile matching the default algorithms of OpenSSH 9.x.
+    pub fn openssh() -> Self {
+        Self::new()
+            .kex_algorithms([
+                "sntrup761x25519-sha512@openssh.com",
+                "curve25519-sha256",
+                "curve25519-sha256@libssh.org",
+                "ecdh-sha2-nistp256",
+                "ecdh-sha2-nistp384",
+                "ecdh-sha2-nistp521",
+                "diffie-hellman-group-exchange-sha256",
+                "diffie-hellman-group16-sha512",
+                "diffie-hellman-group18-sha512",
+                "diffie-hellman-group14-sha256",
+            ])
+            .server_host_key_algorithms([
+                "ssh-ed25519",
+                "ecdsa-sha2-nistp256",
+                "ecdsa-sha2-nistp384",
+                "ecdsa-sha2-nistp521",
+                "sk-ssh-ed25519@openssh.com",
+                "sk-ecdsa-sha2-nistp256@openssh.com",
+                "rsa-sha2-512",
+                "rsa-sha2-256",
+            ])
+            .encryption_algorithms([
+                "chacha20-poly1305@openssh.com",
+                "aes128-ctr",
+                "aes192-ctr",
+                "aes256-ctr",
+                "aes128-gcm@openssh.com",
+                "aes256-gcm@openssh.com",
+            ])
+            .mac_algorithms([
+                "umac-64-etm@openssh.com",
+                "umac-128-etm@openssh.com",
+                "hmac-sha2-256-etm@openssh.com",
+                "hmac-sha2-512-etm@openssh.com",
+                "hmac-sha1-etm@openssh.com",
+                "umac-64@openssh.com",
+                "umac-128@openssh.com",
+                "hmac-sha2-256",
+                "hmac-sha2-512",
+                "hmac-sha1",
+            ])
+            .compression_algorithms(["none", "zlib@openssh.com"])
+    }
+
+    /// A profile restricted to algorithms approved by FIPS 140-3.
+    pub fn fips() -> Self {
+        Self::new()
+            .kex_algorithms([
+                "ecdh-sha2-nistp256",
+                "ecdh-sha2-nistp384",
+                "ecdh-sha2-nistp521",
+                "diffie-hellman-group-exchange-sha256",
+                "diffie-hellman-group16-sha512",
+                "diffie-hellman-group18-sha512",
+                "diffie-hellman-group14-sha256",
+            ])
+            .server_host_key_algorithms([
+                "ecdsa-sha2-nistp256",
+                "ecdsa-sha2-nistp384",
+                "ecdsa-sha2-nistp521",
+                "rsa-sha2-512",
+                "rsa-sha2-256",
+            ])
+            .encryption_algorithms([
+                "aes256-gcm@openssh.com",
+                "aes128-gcm@openssh.com",
+                "aes256-ctr",
+                "aes192-ctr",
+                "aes128-ctr",
+            ])
+            .mac_algorithms([
+                "hmac-sha2-256-etm@openssh.com",
+                "hmac-sha2-512-etm@openssh.com",
+                "hmac-sha2-256",
+                "hmac-sha2-512",
+            ])
+            .compression_algorithms(["none"])
+    }
+
+    setters! {
+        /// Set the key-exchange algorithms.
+        kex_algorithms,
+        /// Set the server host-key algorithms.
+        server_host_key_algorithms,
+        /// Set the client -> server encryption algorithms.
+        encryption_algorithms_client_to_server,
+        /// Set the server -> client encryption algorithms.
+        encryption_algorithms_server_to_client,
+        /// Set the client -> server MAC algorithms.
+        mac_algorithms_client_to_server,
+        /// Set the server -> client MAC algorithms.
+        mac_algorithms_server_to_client,
+        /// Set the client -> server compression algorithms.
+        compression_algorithms_client_to_server,
+        /// Set the server -> client compression algorithms.
+        compression_algorithms_server_to_client,
+    }
+
+    /// Set the encryption algorithms for both directions.
+    pub fn encryption_algorithms<I: IntoIterator<Item = impl Into<String>>>(
+        self,
+        names: I,
+    ) -> Self {
+        let names: Vec<String> = names.into_iter().map(Into::into).collect();
+
+        self.encryption_algorithms_client_to_server(names.clone())
+            .encryption_algorithms_server_to_client(names)
+    }
+
+    /// Set the MAC algorithms for both directions.
+    pub fn mac_algorithms<I: IntoIterator<Item = impl Into<String>>>(self, names: I) -> Self {
+        let names: Vec<String> = names.into_iter().map(Into::into).collect();
+
+        self.mac_algorithms_client_to_server(names.clone())
+            .mac_algorithms_server_to_client(names)
+    }
+
+    /// Set the compression algorithms for both directions.
+    pub fn compression_algorithms<I: IntoIterator<Item = impl Into<String>>>(
+        self,
+        names: I,
+    ) -> Self {
+        let names: Vec<String> = names.into_iter().map(Into::into).collect();
+
+        self.compression_algorithms_client_to_server(names.clone())
+            .compression_algorithms_server_to_client(names)
+    }
+
+    /// Set whether a guessed key-exchange packet follows the `SSH_MSG_KEXINIT` message.
+    pub fn first_kex_packet_follows(mut self, follows: bool) -> Self {
+        self.first_kex_packet_follows = follows;
+        self
+    }
+
+    /// Build the `SSH_MSG_KEXINIT` message, generating the cookie from the `rng`.
+    pub fn build(self, rng: &mut impl CryptoRngCore) -> Result<KexInit<'static>, Error> {
+        let mut cookie = [0u8; 16];
+        rng.fill_bytes(&mut cookie);
+
+        Ok(KexInit {
+            cookie,
+            kex_algorithms: Self::namelist(self.kex_algorithms)?,
+            server_host_key_algorithms: Self::namelist(self.server_host_key_algorithms)?,
+            encryption_algorithms_client_to_server: Self::namelist(
+                self.encryption_algorithms_client_to_server,
+            )?,
+            encryption_algorithms_server_to_client: Self::namelist(
+                self.encryption_algorithms_server_to_client,
+            )?,
+            mac_algorithms_client_to_server: Self::namelist(self.mac_algorithms_client_to_server)?,
+            mac_algorithms_server_to_client: Self::namelist(self.mac_algorithms_server_to_client)?,
+            compression_algorithms_client_to_server: Self::namelist(
+                self.compression_algorithms_client_to_server,
+            )?,
+            compression_algorithms_server_to_client: Self::namelist(
+                self.compression_algorithms_server_to_client,
+            )?,
+            languages_client_to_server: Default::default(),
+            languages_server_to_client: Default::default(),
+            first_kex_packet_follows: self.first_kex_packet_follows.into(),
+        })
+    }
+
+    fn namelist(names: Vec<String>) -> Result<arch::NameList<'static>, Error> {
+        match names.iter().find(|name| !Self::is_valid(name)) {
+            Some(name) => Err(Error::AlgorithmName(name.clone())),
+            None => Ok(names.into_iter().collect()),
+        }
+    }
+
+    /// Whether the `name` conforms to the algorithm naming rules,
+    /// see <https://datatracker.ietf.org/doc/html/rfc4251#section-6>.
+    fn is_valid(name: &str) -> bool {
+        let charset = name
+            .bytes()
+            .all(|byte| byte.is_ascii_graphic() && byte != b',');
+
+        let domain = match name.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty() && !domain.is_empty() && !domain.contains('@')
+            }
+            None => true,
+        };
+
+        !name.is_empty() && name.len() <= 64 && charset && domain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use rand_core::OsRng;
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(KexInitBuilder::modern(), KexInitBuilder::openssh())]
+    #[case(KexInitBuilder::openssh(), KexInitBuilder::modern())]
+    #[case(KexInitBuilder::fips(), KexInitBuilder::openssh())]
+    fn it_builds_compatible_profiles(
+        #[case] client: KexInitBuilder,
+        #[case] server: KexInitBuilder,
+    ) {
+        let client = client.build(&mut OsRng).unwrap();
+        let server = server.build(&mut OsRng).unwrap();
+
+        assert_ne!(client.cookie, server.cookie);
+        KexInit::negotiate(&client, &server).unwrap();
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("with space")]
+    #[case("with,comma")]
+    #[case("@example.com")]
+    #[case("local@")]
+    #[case("two@at@example.com")]
+    #[case("a-very-long-algorithm-name-which-goes-way-past-the-limit@example.com")]
+    fn it_rejects_invalid_names(#[case] name: &str) {
+        let builder = KexInitBuilder::modern().kex_algorithms(["curve25519-sha256", name]);
+
+        assert!(matches!(
+            builder.build(&mut OsRng),
+            Err(Error::AlgorithmName(rejected)) if rejected == name
+        ));
+    }
+}
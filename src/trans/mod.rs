@@ -0,0 +1,1110 @@
+//! Messages involved in the SSH's **transport** (`SSH-TRANS`) part of the protocol,
+//! as defined in the [RFC 4253](https://datatracker.ietf.org/doc/html/rfc4253),
+//! [RFC 4419](https://datatracker.ietf.org/doc/html/rfc4419), [RFC 4462](https://datatracker.ietf.org/doc/html/rfc4462),
+//! [RFC 5656](https://datatracker.ietf.org/doc/html/rfc5656) and [RFC 8308](https://datatracker.ietf.org/doc/html/rfc8308).
+
+use binrw::binrw;
+
+use crate::{arch, Error};
+
+#[cfg(feature = "rand_core")]
+mod builder;
+#[cfg(feature = "rand_core")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rand_core")))]
+pub use builder::KexInitBuilder;
+
+/// The `SSH_MSG_DISCONNECT` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-11.1>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 1_u8)]
+pub struct Disconnect<'b> {
+    /// Reason for disconnection.
+    pub reason: DisconnectReason,
+
+    /// Description of the reason for disconnection.
+    pub description: arch::Utf8<'b>,
+
+    /// Language tag.
+    pub language: arch::Ascii<'b>,
+}
+
+/// The `reason` for disconnect in the `SSH_MSG_DISCONNECT` message.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big)]
+pub enum DisconnectReason {
+    /// `SSH_DISCONNECT_HOST_NOT_ALLOWED_TO_CONNECT`.
+    #[brw(magic = 1_u32)]
+    HostNotAllowedToConnect,
+
+    /// `SSH_DISCONNECT_PROTOCOL_ERROR`.
+    #[brw(magic = 2_u32)]
+    ProtocolError,
+
+    /// `SSH_DISCONNECT_KEY_EXCHANGE_FAILED`.
+    #[brw(magic = 3_u32)]
+    KeyExchangeFailed,
+
+    /// `SSH_DISCONNECT_RESERVED`.
+    #[brw(magic = 4_u32)]
+    Reserved,
+
+    /// `SSH_DISCONNECT_MAC_ERROR`.
+    #[brw(magic = 5_u32)]
+    MacError,
+
+    /// `SSH_DISCONNECT_COMPRESSION_ERROR`.
+    #[brw(magic = 6_u32)]
+    CompressionError,
+
+    /// `SSH_DISCONNECT_SERVICE_NOT_AVAILABLE`.
+    #[brw(magic = 7_u32)]
+    ServiceNotAvailable,
+
+    /// `SSH_DISCONNECT_PROTOCOL_VERSION_NOT_SUPPORTED`.
+    #[brw(magic = 8_u32)]
+    ProtocolVersionNotSupported,
+
+    /// `SSH_DISCONNECT_HOST_KEY_NOT_VERIFIABLE`.
+    #[brw(magic = 9_u32)]
+    HostKeyNotVerifiable,
+
+    /// `SSH_DISCONNECT_CONNECTION_LOST`.
+    #[brw(magic = 10_u32)]
+    ConnectionLost,
+
+    /// `SSH_DISCONNECT_BY_APPLICATION`.
+    #[brw(magic = 11_u32)]
+    ByApplication,
+
+    /// `SSH_DISCONNECT_TOO_MANY_CONNECTIONS`.
+    #[brw(magic = 12_u32)]
+    TooManyConnections,
+
+    /// `SSH_DISCONNECT_AUTH_CANCELLED_BY_USER`.
+    #[brw(magic = 13_u32)]
+    AuthCancelledByUser,
+
+    /// `SSH_DISCONNECT_NO_MORE_AUTH_METHODS_AVAILABLE`.
+    #[brw(magic = 14_u32)]
+    NoMoreAuthMethodsAvailable,
+
+    /// `SSH_DISCONNECT_ILLEGAL_USER_NAME`.
+    #[brw(magic = 15_u32)]
+    IllegalUserName,
+
+    /// Any other disconnect reason, may be non-standard.
+    ///
+    /// The 'reason' values in the range of `0xFE000000`
+    /// through `0xFFFFFFFF` are reserved for PRIVATE USE.
+    Other(u32),
+}
+
+/// The `SSH_MSG_IGNORE` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-11.2>.
+#[binrw]
+#[derive(Debug, Default, Clone)]
+#[brw(big, magic = 2_u8)]
+pub struct Ignore<'b> {
+    /// A random blob of data to ignore.
+    pub data: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_UNIMPLEMENTED` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-11.4>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 3_u8)]
+pub struct Unimplemented {
+    /// Packet sequence number of rejected message.
+    pub seq: u32,
+}
+
+/// The `SSH_MSG_DEBUG` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-11.3>.
+#[binrw]
+#[derive(Debug, Default, Clone)]
+#[brw(big, magic = 4_u8)]
+pub struct Debug<'b> {
+    /// Whether the debug data should be forcefully displayed.
+    pub always_display: arch::Bool,
+
+    /// The debug message.
+    pub message: arch::Utf8<'b>,
+
+    /// Language tag.
+    pub language: arch::Ascii<'b>,
+}
+
+/// The `SSH2_MSG_PING` message, only to be sent to peers
+/// having advertised the `ping@openssh.com` extension.
+///
+/// see <https://github.com/openssh/openssh-portable/blob/master/PROTOCOL>, section 1.11.
+#[binrw]
+#[derive(Debug, Default, Clone)]
+#[brw(big, magic = 192_u8)]
+pub struct Ping<'b> {
+    /// A blob of data to be echoed back by the peer.
+    pub data: arch::Bytes<'b>,
+}
+
+impl Ping<'_> {
+    /// Produce the [`Pong`] reply to this message, echoing its data.
+    pub fn pong(&self) -> Pong<'_> {
+        Pong {
+            data: self.data.as_borrow(),
+        }
+    }
+}
+
+/// The `SSH2_MSG_PONG` message.
+///
+/// see <https://github.com/openssh/openssh-portable/blob/master/PROTOCOL>, section 1.11.
+#[binrw]
+#[derive(Debug, Default, Clone)]
+#[brw(big, magic = 193_u8)]
+pub struct Pong<'b> {
+    /// The data echoed back from the [`Ping`] message.
+    pub data: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_SERVICE_REQUEST` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-10>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 5_u8)]
+pub struct ServiceRequest<'b> {
+    /// The service name to request.
+    pub service_name: arch::Ascii<'b>,
+}
+
+/// The `SSH_MSG_SERVICE_ACCEPT` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-10>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 6_u8)]
+pub struct ServiceAccept<'b> {
+    /// Service name accepted to be requested.
+    pub service_name: arch::Ascii<'b>,
+}
+
+/// The `SSH_MSG_EXT_INFO` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc8308#section-2.3>.
+#[binrw]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[brw(big, magic = 7_u8)]
+pub struct ExtInfo {
+    #[bw(calc = extensions.len() as u32)]
+    num_extensions: u32,
+
+    /// The extensions advertised by the sender.
+    #[br(count = num_extensions)]
+    pub extensions: Vec<Extension<'static>>,
+}
+
+/// An extension in the `SSH_MSG_EXT_INFO` message, prefixed by its name.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc8308#section-3>.
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[brw(big)]
+pub enum Extension<'b> {
+    /// The `server-sig-algs` extension,
+    /// as defined in [RFC8308 section 3.1](https://datatracker.ietf.org/doc/html/rfc8308#section-3.1).
+    #[brw(magic = b"\x00\x00\x00\x0fserver-sig-algs")]
+    ServerSigAlgs {
+        /// Public key algorithms accepted by the server for authentication.
+        algorithms: arch::NameList<'b>,
+    },
+
+    /// The `delay-compression` extension,
+    /// as defined in [RFC8308 section 3.2](https://datatracker.ietf.org/doc/html/rfc8308#section-3.2).
+    #[brw(magic = b"\x00\x00\x00\x11delay-compression")]
+    DelayCompression {
+        #[bw(calc = (8 + compression_client_to_server.0.len() + compression_server_to_client.0.len()) as u32)]
+        _size: u32,
+
+        /// Client -> server compression algorithms.
+        compression_client_to_server: arch::NameList<'b>,
+
+        /// Server -> client compression algorithms.
+        compression_server_to_client: arch::NameList<'b>,
+    },
+
+    /// The `no-flow-control` extension,
+    /// as defined in [RFC8308 section 3.3](https://datatracker.ietf.org/doc/html/rfc8308#section-3.3).
+    #[brw(magic = b"\x00\x00\x00\x0fno-flow-control")]
+    NoFlowControl {
+        /// Either `p` for _preferred_ or `s` for _supported_.
+        choice: arch::Ascii<'b>,
+    },
+
+    /// The `elevation` extension,
+    /// as defined in [RFC8308 section 3.4](https://datatracker.ietf.org/doc/html/rfc8308#section-3.4).
+    #[brw(magic = b"\x00\x00\x00\x09elevation")]
+    Elevation {
+        /// Either `y` for _yes_, `n` for _no_ or `d` for _default_.
+        choice: arch::Ascii<'b>,
+    },
+
+    /// The `publickey-hostbound@openssh.com` extension,
+    /// as defined in [OpenSSH's PROTOCOL section 1.10](https://github.com/openssh/openssh-portable/blob/master/PROTOCOL).
+    #[brw(magic = b"\x00\x00\x00\x1fpublickey-hostbound@openssh.com")]
+    PublickeyHostbound {
+        /// The version of the extension, currently `0`.
+        version: arch::Ascii<'b>,
+    },
+
+    /// The `ping@openssh.com` extension,
+    /// as defined in [OpenSSH's PROTOCOL section 1.11](https://github.com/openssh/openssh-portable/blob/master/PROTOCOL).
+    #[brw(magic = b"\x00\x00\x00\x10ping@openssh.com")]
+    Ping {
+        /// The version of the extension, currently `0`.
+        version: arch::Ascii<'b>,
+    },
+
+    /// Any other extension unknown to this crate.
+    Unknown {
+        /// Name of the extension.
+        name: arch::Ascii<'b>,
+
+        /// Raw value of the extension.
+        value: arch::Bytes<'b>,
+    },
+}
+
+impl ExtInfo {
+    /// Whether the sender supports the `SSH2_MSG_PING` and `SSH2_MSG_PONG` messages.
+    pub fn ping(&self) -> bool {
+        self.extensions
+            .iter()
+            .any(|extension| matches!(extension, Extension::Ping { version } if &**version == "0"))
+    }
+
+    /// Advertise support for the `SSH2_MSG_PING` and `SSH2_MSG_PONG` messages.
+    pub fn with_ping(mut self) -> Self {
+        if !self.ping() {
+            self.extensions.push(Extension::Ping {
+                version: arch::ascii!("0"),
+            });
+        }
+
+        self
+    }
+}
+
+impl Extension<'_> {
+    /// Get the [`Extension`]'s name.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::ServerSigAlgs { .. } => "server-sig-algs",
+            Self::DelayCompression { .. } => "delay-compression",
+            Self::NoFlowControl { .. } => "no-flow-control",
+            Self::Elevation { .. } => "elevation",
+            Self::PublickeyHostbound { .. } => "publickey-hostbound@openssh.com",
+            Self::Ping { .. } => "ping@openssh.com",
+            Self::Unknown { name, .. } => name,
+        }
+    }
+}
+
+/// The `SSH_MSG_KEXINIT` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-7.1>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 20_u8)]
+pub struct KexInit<'b> {
+    /// The kex-init cookie.
+    pub cookie: [u8; 16],
+
+    /// Kex algorithms.
+    pub kex_algorithms: arch::NameList<'b>,
+
+    /// Server host-key algorithms.
+    pub server_host_key_algorithms: arch::NameList<'b>,
+
+    /// Client -> server encryption algorithms.
+    pub encryption_algorithms_client_to_server: arch::NameList<'b>,
+
+    /// Server -> client encryption algorithms.
+    pub encryption_algorithms_server_to_client: arch::NameList<'b>,
+
+    /// Client -> server MAC algorithms.
+    pub mac_algorithms_client_to_server: arch::NameList<'b>,
+
+    /// Server -> client MAC algorithms.
+    pub mac_algorithms_server_to_client: arch::NameList<'b>,
+
+    /// Client -> server compression algorithms.
+    pub compression_algorithms_client_to_server: arch::NameList<'b>,
+
+    /// Server -> client compression algorithms.
+    pub compression_algorithms_server_to_client: arch::NameList<'b>,
+
+    /// Client -> server languages.
+    pub languages_client_to_server: arch::NameList<'b>,
+
+    /// Server -> client languages.
+    pub languages_server_to_client: arch::NameList<'b>,
+
+    /// Whether the first kex packet follows.
+    pub first_kex_packet_follows: arch::Bool,
+
+    #[bw(calc = 0)]
+    _reserved: u32,
+}
+
+impl KexInit<'_> {
+    /// The `ext-info-c` pseudo-algorithm, advertising support for `SSH_MSG_EXT_INFO` by the client.
+    pub const EXT_INFO_C: arch::Ascii<'static> = arch::ascii!("ext-info-c");
+
+    /// The `ext-info-s` pseudo-algorithm, advertising support for `SSH_MSG_EXT_INFO` by the server.
+    pub const EXT_INFO_S: arch::Ascii<'static> = arch::ascii!("ext-info-s");
+
+    /// The `kex-strict-c-v00@openssh.com` pseudo-algorithm, advertising support for strict key-exchange by the client.
+    pub const KEX_STRICT_C: arch::Ascii<'static> = arch::ascii!("kex-strict-c-v00@openssh.com");
+
+    /// The `kex-strict-s-v00@openssh.com` pseudo-algorithm, advertising support for strict key-exchange by the server.
+    pub const KEX_STRICT_S: arch::Ascii<'static> = arch::ascii!("kex-strict-s-v00@openssh.com");
+
+    /// Whether the client advertised support for `SSH_MSG_EXT_INFO`,
+    /// only meaningful in the first `SSH_MSG_KEXINIT` of the session.
+    pub fn ext_info_c(&self) -> bool {
+        self.has_kex_algorithm(&Self::EXT_INFO_C)
+    }
+
+    /// Whether the server advertised support for `SSH_MSG_EXT_INFO`,
+    /// only meaningful in the first `SSH_MSG_KEXINIT` of the session.
+    pub fn ext_info_s(&self) -> bool {
+        self.has_kex_algorithm(&Self::EXT_INFO_S)
+    }
+
+    /// Advertise support for `SSH_MSG_EXT_INFO` as a client.
+    pub fn with_ext_info_c(self) -> Self {
+        self.with_kex_algorithm(&Self::EXT_INFO_C)
+    }
+
+    /// Advertise support for `SSH_MSG_EXT_INFO` as a server.
+    pub fn with_ext_info_s(self) -> Self {
+        self.with_kex_algorithm(&Self::EXT_INFO_S)
+    }
+
+    /// Whether the client advertised support for strict key-exchange,
+    /// only meaningful in the first `SSH_MSG_KEXINIT` of the session.
+    pub fn kex_strict_c(&self) -> bool {
+        self.has_kex_algorithm(&Self::KEX_STRICT_C)
+    }
+
+    /// Whether the server advertised support for strict key-exchange,
+    /// only meaningful in the first `SSH_MSG_KEXINIT` of the session.
+    pub fn kex_strict_s(&self) -> bool {
+        self.has_kex_algorithm(&Self::KEX_STRICT_S)
+    }
+
+    /// Advertise support for strict key-exchange as a client.
+    pub fn with_kex_strict_c(self) -> Self {
+        self.with_kex_algorithm(&Self::KEX_STRICT_C)
+    }
+
+    /// Advertise support for strict key-exchange as a server.
+    pub fn with_kex_strict_s(self) -> Self {
+        self.with_kex_algorithm(&Self::KEX_STRICT_S)
+    }
+
+    fn has_kex_algorithm(&self, name: &str) -> bool {
+        self.kex_algorithms
+            .into_iter()
+            .any(|algorithm| &*algorithm == name)
+    }
+
+    fn with_kex_algorithm(mut self, name: &str) -> Self {
+        if !self.has_kex_algorithm(name) {
+            // Pseudo-algorithms are appended to never be selected by the negotiation
+            self.kex_algorithms = self
+                .kex_algorithms
+                .into_iter()
+                .map(|algorithm| algorithm.into_string())
+                .chain(std::iter::once(name.to_owned()))
+                .collect();
+        }
+
+        self
+    }
+}
+
+impl KexInit<'_> {
+    /// Negotiate the algorithms to be used for the session from
+    /// the `client`'s and `server`'s `SSH_MSG_KEXINIT` messages.
+    ///
+    /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-7.1>.
+    pub fn negotiate<'a>(
+        client: &'a KexInit<'_>,
+        server: &KexInit<'_>,
+    ) -> Result<Negotiated<'a>, Error> {
+        let mut failed = Slot::Kex;
+        let (kex, server_host_key) = client
+            .kex_algorithms
+            .into_iter()
+            .filter(|kex| server.kex_algorithms.into_iter().any(|other| kex == &other))
+            .find_map(|kex| {
+                failed = Slot::ServerHostKey;
+
+                client
+                    .server_host_key_algorithms
+                    .into_iter()
+                    .filter(|key| Negotiated::is_compatible(&kex, key))
+                    .find(|key| {
+                        server
+                            .server_host_key_algorithms
+                            .into_iter()
+                            .any(|other| key == &other)
+                    })
+                    .map(|key| (kex, key))
+            })
+            .ok_or(Error::Negotiation(failed))?;
+
+        let encryption_client_to_server = client
+            .encryption_algorithms_client_to_server
+            .preferred_in(&server.encryption_algorithms_client_to_server)
+            .ok_or(Error::Negotiation(Slot::EncryptionClientToServer))?;
+        let encryption_server_to_client = client
+            .encryption_algorithms_server_to_client
+            .preferred_in(&server.encryption_algorithms_server_to_client)
+            .ok_or(Error::Negotiation(Slot::EncryptionServerToClient))?;
+
+        let mac_client_to_server = Negotiated::mac(
+            &encryption_client_to_server,
+            &client.mac_algorithms_client_to_server,
+            &server.mac_algorithms_client_to_server,
+        )
+        .ok_or(Error::Negotiation(Slot::MacClientToServer))?;
+        let mac_server_to_client = Negotiated::mac(
+            &encryption_server_to_client,
+            &client.mac_algorithms_server_to_client,
+            &server.mac_algorithms_server_to_client,
+        )
+        .ok_or(Error::Negotiation(Slot::MacServerToClient))?;
+
+        let compression_client_to_server = client
+            .compression_algorithms_client_to_server
+            .preferred_in(&server.compression_algorithms_client_to_server)
+            .ok_or(Error::Negotiation(Slot::CompressionClientToServer))?;
+        let compression_server_to_client = client
+            .compression_algorithms_server_to_client
+            .preferred_in(&server.compression_algorithms_server_to_client)
+            .ok_or(Error::Negotiation(Slot::CompressionServerToClient))?;
+
+        // The guess is right only if both sides prefer the same kex and host-key algorithms
+        let guess_matches = client.kex_algorithms.into_iter().next()
+            == server.kex_algorithms.into_iter().next()
+            && client.server_host_key_algorithms.into_iter().next()
+                == server.server_host_key_algorithms.into_iter().next();
+
+        Ok(Negotiated {
+            kex,
+            server_host_key,
+            encryption_client_to_server,
+            encryption_server_to_client,
+            mac_client_to_server,
+            mac_server_to_client,
+            compression_client_to_server,
+            compression_server_to_client,
+            guess_matches,
+        })
+    }
+}
+
+/// An algorithm slot of the `SSH_MSG_KEXINIT` message, subject to negotiation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    /// The key-exchange algorithm.
+    Kex,
+
+    /// The server host-key algorithm.
+    ServerHostKey,
+
+    /// The client -> server encryption algorithm.
+    EncryptionClientToServer,
+
+    /// The server -> client encryption algorithm.
+    EncryptionServerToClient,
+
+    /// The client -> server MAC algorithm.
+    MacClientToServer,
+
+    /// The server -> client MAC algorithm.
+    MacServerToClient,
+
+    /// The client -> server compression algorithm.
+    CompressionClientToServer,
+
+    /// The server -> client compression algorithm.
+    CompressionServerToClient,
+}
+
+impl std::fmt::Display for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Kex => "key-exchange",
+            Self::ServerHostKey => "server host-key",
+            Self::EncryptionClientToServer => "client -> server encryption",
+            Self::EncryptionServerToClient => "server -> client encryption",
+            Self::MacClientToServer => "client -> server MAC",
+            Self::MacServerToClient => "server -> client MAC",
+            Self::CompressionClientToServer => "client -> server compression",
+            Self::CompressionServerToClient => "server -> client compression",
+        })
+    }
+}
+
+/// The algorithms negotiated from both `SSH_MSG_KEXINIT` messages, see [`KexInit::negotiate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Negotiated<'b> {
+    /// Key-exchange algorithm.
+    pub kex: arch::Ascii<'b>,
+
+    /// Server host-key algorithm.
+    pub server_host_key: arch::Ascii<'b>,
+
+    /// Client -> server encryption algorithm.
+    pub encryption_client_to_server: arch::Ascii<'b>,
+
+    /// Server -> client encryption algorithm.
+    pub encryption_server_to_client: arch::Ascii<'b>,
+
+    /// Client -> server MAC algorithm, or [`None`] if implied by the encryption algorithm.
+    pub mac_client_to_server: Option<arch::Ascii<'b>>,
+
+    /// Server -> client MAC algorithm, or [`None`] if implied by the encryption algorithm.
+    pub mac_server_to_client: Option<arch::Ascii<'b>>,
+
+    /// Client -> server compression algorithm.
+    pub compression_client_to_server: arch::Ascii<'b>,
+
+    /// Server -> client compression algorithm.
+    pub compression_server_to_client: arch::Ascii<'b>,
+
+    /// Whether the client and the server prefer the same kex and host-key algorithms.
+    guess_matches: bool,
+}
+
+impl<'b> Negotiated<'b> {
+    /// Whether the key-exchange packet following the `peer`'s `SSH_MSG_KEXINIT` must be ignored,
+    /// that is when `first_kex_packet_follows` is set and the client and the server
+    /// do not prefer the same kex and host-key algorithms, the first one of each list.
+    ///
+    /// see <https://datatracker.ietf.org/doc/html/rfc4253#section-7>.
+    pub fn ignore_guessed_kex_packet(&self, peer: &KexInit<'_>) -> bool {
+        *peer.first_kex_packet_follows && !self.guess_matches
+    }
+
+    /// Whether the `kex` algorithm can be used with the `host_key` algorithm.
+    fn is_compatible(kex: &str, host_key: &str) -> bool {
+        // The `null` host-key cannot sign, it is only usable with GSS-API key-exchange
+        host_key != "null" || kex.starts_with("gss-")
+    }
+
+    /// Negotiate the MAC algorithm, given the negotiated `encryption` algorithm.
+    fn mac(
+        encryption: &arch::Ascii<'b>,
+        client: &'b arch::NameList<'_>,
+        server: &arch::NameList<'_>,
+    ) -> Option<Option<arch::Ascii<'b>>> {
+        match &**encryption {
+            // The OpenSSH AEAD ciphers ignore the MAC negotiation
+            "aes128-gcm@openssh.com"
+            | "aes256-gcm@openssh.com"
+            | "chacha20-poly1305@openssh.com" => Some(None),
+            // The RFC5647 AEAD ciphers must be negotiated as the MAC algorithm too
+            name if name.starts_with("AEAD_") => client
+                .preferred_in(server)
+                .filter(|mac| mac == encryption)
+                .map(Some),
+            _ => client.preferred_in(server).map(Some),
+        }
+    }
+}
+
+/// The `SSH_MSG_NEWKEYS` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-7.3>.
+#[binrw]
+#[derive(Debug, Default, Clone)]
+#[brw(big, magic = 21_u8)]
+pub struct NewKeys;
+
+/// The `SSH_MSG_KEXDH_INIT` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-8>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 30_u8)]
+pub struct KexdhInit<'b> {
+    /// Exchange value sent by the client.
+    pub e: arch::MpInt<'b>,
+}
+
+/// The `SSH_MSG_KEXDH_REPLY` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4253#section-8>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 31_u8)]
+pub struct KexdhReply<'b> {
+    /// Server's public host key.
+    pub k_s: arch::Bytes<'b>,
+
+    /// Exchange value sent by the server.
+    pub f: arch::MpInt<'b>,
+
+    /// Signature of the exchange hash.
+    pub signature: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_KEX_ECDH_INIT` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc5656#section-4>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 30_u8)]
+pub struct KexEcdhInit<'b> {
+    /// Client's ephemeral public key octet string.
+    pub q_c: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_KEX_ECDH_REPLY` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc5656#section-4>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 31_u8)]
+pub struct KexEcdhReply<'b> {
+    /// Server's public host key.
+    pub k_s: arch::Bytes<'b>,
+
+    /// Server's ephemeral public key octet string.
+    pub q_s: arch::Bytes<'b>,
+
+    /// Signature of the exchange hash.
+    pub signature: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_KEX_DH_GEX_REQUEST_OLD` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4419#section-5>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 30_u8)]
+pub struct KexDhGexRequestOld {
+    /// Preferred size in bits of the group the server will send.
+    pub n: u32,
+}
+
+/// The `SSH_MSG_KEX_DH_GEX_REQUEST` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4419#section-3>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 34_u8)]
+pub struct KexDhGexRequest {
+    /// Minimal size in bits of an acceptable group.
+    pub min: u32,
+
+    /// Preferred size in bits of the group the server will send.
+    pub n: u32,
+
+    /// Maximal size in bits of an acceptable group.
+    pub max: u32,
+}
+
+/// The `SSH_MSG_KEX_DH_GEX_GROUP` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4419#section-3>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 31_u8)]
+pub struct KexDhGexGroup<'b> {
+    /// Safe prime of the group.
+    pub p: arch::MpInt<'b>,
+
+    /// Generator for the subgroup.
+    pub g: arch::MpInt<'b>,
+}
+
+/// The `SSH_MSG_KEX_DH_GEX_INIT` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4419#section-3>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 32_u8)]
+pub struct KexDhGexInit<'b> {
+    /// Exchange value sent by the client.
+    pub e: arch::MpInt<'b>,
+}
+
+/// The `SSH_MSG_KEX_DH_GEX_REPLY` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4419#section-3>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 33_u8)]
+pub struct KexDhGexReply<'b> {
+    /// Server's public host key.
+    pub k_s: arch::Bytes<'b>,
+
+    /// Exchange value sent by the server.
+    pub f: arch::MpInt<'b>,
+
+    /// Signature of the exchange hash.
+    pub signature: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_KEXGSS_INIT` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-2.1>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 30_u8)]
+pub struct KexgssInit<'b> {
+    /// Token produced by the client's `GSS_Init_sec_context()`.
+    pub output_token: arch::Bytes<'b>,
+
+    /// Exchange value sent by the client.
+    pub e: arch::MpInt<'b>,
+}
+
+/// The `SSH_MSG_KEXGSS_CONTINUE` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-2.1>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 31_u8)]
+pub struct KexgssContinue<'b> {
+    /// Token produced by the peer's security context routine.
+    pub output_token: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_KEXGSS_COMPLETE` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-2.1>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 32_u8)]
+pub struct KexgssComplete<'b> {
+    /// Exchange value sent by the server.
+    pub f: arch::MpInt<'b>,
+
+    /// The MIC of the exchange hash, produced by `GSS_GetMIC()`.
+    pub per_msg_token: arch::Bytes<'b>,
+
+    #[bw(calc = arch::Bool::from(output_token.is_some()))]
+    has_output_token: arch::Bool,
+
+    /// The optional final token produced by the server's `GSS_Accept_sec_context()`.
+    #[br(if(*has_output_token))]
+    pub output_token: Option<arch::Bytes<'b>>,
+}
+
+/// The `SSH_MSG_KEXGSS_HOSTKEY` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-2.1>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 33_u8)]
+pub struct KexgssHostkey<'b> {
+    /// Server's public host key.
+    pub k_s: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_KEXGSS_ERROR` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-2.1>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 34_u8)]
+pub struct KexgssError<'b> {
+    /// The GSS-API major status code.
+    pub major_status: u32,
+
+    /// The GSS-API minor status code.
+    pub minor_status: u32,
+
+    /// Human-readable description of the error.
+    pub message: arch::Utf8<'b>,
+
+    /// Language tag.
+    pub language: arch::Ascii<'b>,
+}
+
+/// The `SSH_MSG_KEXGSS_GROUPREQ` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-2.2>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 40_u8)]
+pub struct KexgssGroupreq {
+    /// Minimal size in bits of an acceptable group.
+    pub min: u32,
+
+    /// Preferred size in bits of the group the server will send.
+    pub n: u32,
+
+    /// Maximal size in bits of an acceptable group.
+    pub max: u32,
+}
+
+/// The `SSH_MSG_KEXGSS_GROUP` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-2.2>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 41_u8)]
+pub struct KexgssGroup<'b> {
+    /// Safe prime of the group.
+    pub p: arch::MpInt<'b>,
+
+    /// Generator for the subgroup.
+    pub g: arch::MpInt<'b>,
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unimplemented)]
+    use binrw::{BinRead, BinWrite};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::packet::tests::kexinit;
+
+    #[rstest]
+    #[case(Extension::ServerSigAlgs { algorithms: ["rsa-sha2-256", "ssh-ed25519"].into_iter().collect() })]
+    #[case(Extension::DelayCompression {
+        compression_client_to_server: ["zlib@openssh.com", "none"].into_iter().collect(),
+        compression_server_to_client: ["none"].into_iter().collect(),
+    })]
+    #[case(Extension::NoFlowControl { choice: arch::ascii!("p") })]
+    #[case(Extension::Elevation { choice: arch::ascii!("d") })]
+    #[case(Extension::PublickeyHostbound { version: arch::ascii!("0") })]
+    #[case(Extension::Ping { version: arch::ascii!("0") })]
+    #[case(Extension::Unknown { name: arch::ascii!("custom@example.com"), value: arch::Bytes::borrowed(b"value") })]
+    fn it_roundtrips_extensions(#[case] extension: Extension<'static>) {
+        let ext = ExtInfo {
+            extensions: vec![extension.clone()],
+        };
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        ext.write(&mut buffer).unwrap();
+
+        // The extension's value is always a `string`, whatever its inner structure
+        let value = &buffer.get_ref()[1 + 4 + 4 + extension.name().len()..];
+        assert_eq!(
+            u32::from_be_bytes(value[..4].try_into().unwrap()) as usize,
+            value.len() - 4
+        );
+
+        buffer.set_position(0);
+        assert_eq!(ExtInfo::read(&mut buffer).unwrap(), ext);
+    }
+
+    /// Encode the `message`, decode it back and check the decoded one encodes identically.
+    fn roundtrip<T>(message: &T) -> T
+    where
+        T: for<'a> BinRead<Args<'a> = ()> + for<'a> BinWrite<Args<'a> = ()>,
+        T: binrw::meta::ReadEndian + binrw::meta::WriteEndian,
+    {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        message.write(&mut buffer).unwrap();
+        let encoded = buffer.into_inner();
+
+        let decoded = T::read(&mut std::io::Cursor::new(&encoded)).unwrap();
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        decoded.write(&mut buffer).unwrap();
+        assert_eq!(buffer.into_inner(), encoded);
+
+        decoded
+    }
+
+    #[test]
+    fn it_roundtrips_gex_messages() {
+        let request = roundtrip(&KexDhGexRequestOld { n: 4096 });
+        assert_eq!(request.n, 4096);
+
+        let request = roundtrip(&KexDhGexRequest {
+            min: 2048,
+            n: 4096,
+            max: 8192,
+        });
+        assert_eq!((request.min, request.n, request.max), (2048, 4096, 8192));
+
+        let group = roundtrip(&KexDhGexGroup {
+            p: arch::MpInt::positive(&[0xff; 16]),
+            g: arch::MpInt::positive(&[0x02]),
+        });
+        assert_eq!(group.p.as_ref(), [&[0x00][..], &[0xff; 16]].concat());
+        assert_eq!(group.g.as_ref(), [0x02]);
+
+        let init = roundtrip(&KexDhGexInit {
+            e: arch::MpInt::positive(&[0x01; 16]),
+        });
+        assert_eq!(init.e.as_ref(), [0x01; 16]);
+
+        let reply = roundtrip(&KexDhGexReply {
+            k_s: arch::Bytes::borrowed(b"host-key"),
+            f: arch::MpInt::positive(&[0x02; 16]),
+            signature: arch::Bytes::borrowed(b"signature"),
+        });
+        assert_eq!(reply.k_s.as_ref(), b"host-key");
+        assert_eq!(reply.f.as_ref(), [0x02; 16]);
+        assert_eq!(reply.signature.as_ref(), b"signature");
+    }
+
+    #[test]
+    fn it_negotiates_with_client_preference() {
+        let client = kexinit(
+            &["curve25519-sha256", "diffie-hellman-group14-sha256"],
+            &["ssh-ed25519", "rsa-sha2-256"],
+            &["aes256-ctr", "aes128-ctr"],
+            &["hmac-sha2-512", "hmac-sha2-256"],
+        )
+        .with_ext_info_c()
+        .with_kex_strict_c();
+        let server = kexinit(
+            &["diffie-hellman-group14-sha256", "curve25519-sha256"],
+            &["rsa-sha2-256", "ssh-ed25519"],
+            &["aes128-ctr", "aes256-ctr"],
+            &["hmac-sha2-256", "hmac-sha2-512"],
+        )
+        .with_ext_info_s()
+        .with_kex_strict_s();
+
+        let negotiated = KexInit::negotiate(&client, &server).unwrap();
+        assert_eq!(&*negotiated.kex, "curve25519-sha256");
+        assert_eq!(&*negotiated.server_host_key, "ssh-ed25519");
+        assert_eq!(&*negotiated.encryption_client_to_server, "aes256-ctr");
+        assert_eq!(
+            negotiated.mac_server_to_client.as_deref(),
+            Some("hmac-sha2-512")
+        );
+        assert_eq!(&*negotiated.compression_client_to_server, "none");
+        assert!(!negotiated.ignore_guessed_kex_packet(&client));
+        assert!(!negotiated.ignore_guessed_kex_packet(&server));
+    }
+
+    #[test]
+    fn it_negotiates_host_key_compatible_with_kex() {
+        let client = kexinit(
+            &[
+                "curve25519-sha256",
+                "gss-curve25519-sha256-toWM5Slw5Ew8Mqkay+al2g==",
+            ],
+            &["null"],
+            &["aes128-ctr"],
+            &["hmac-sha2-256"],
+        );
+        let negotiated = KexInit::negotiate(&client, &client).unwrap();
+        assert_eq!(
+            &*negotiated.kex,
+            "gss-curve25519-sha256-toWM5Slw5Ew8Mqkay+al2g=="
+        );
+        assert_eq!(&*negotiated.server_host_key, "null");
+    }
+
+    #[test]
+    fn it_skips_mac_for_aead() {
+        let client = kexinit(
+            &["curve25519-sha256"],
+            &["ssh-ed25519"],
+            &["chacha20-poly1305@openssh.com"],
+            &[],
+        );
+        let negotiated = KexInit::negotiate(&client, &client).unwrap();
+        assert_eq!(negotiated.mac_client_to_server, None);
+    }
+
+    #[rstest]
+    #[case(kexinit(&["curve448-sha512"], &["ssh-ed25519"], &["aes128-ctr"], &["hmac-sha2-256"]), Slot::Kex)]
+    #[case(kexinit(&["curve25519-sha256"], &["null"], &["aes128-ctr"], &["hmac-sha2-256"]), Slot::ServerHostKey)]
+    #[case(kexinit(&["curve25519-sha256"], &["ssh-ed25519"], &["aes256-ctr"], &["hmac-sha2-256"]), Slot::EncryptionClientToServer)]
+    #[case(kexinit(&["curve25519-sha256"], &["ssh-ed25519"], &["aes128-ctr"], &["hmac-sha2-512"]), Slot::MacClientToServer)]
+    fn it_names_the_failed_slot(#[case] server: KexInit<'static>, #[case] slot: Slot) {
+        let client = kexinit(
+            &["curve25519-sha256"],
+            &["ssh-ed25519", "null"],
+            &["aes128-ctr"],
+            &["hmac-sha2-256"],
+        );
+
+        assert!(matches!(
+            KexInit::negotiate(&client, &server),
+            Err(Error::Negotiation(failed)) if failed == slot
+        ));
+    }
+
+    #[rstest]
+    #[case(&["curve25519-sha256", "ecdh-sha2-nistp256"], &["ssh-ed25519"], true, false, false, false)]
+    #[case(&["ecdh-sha2-nistp256", "curve25519-sha256"], &["ssh-ed25519"], true, false, true, false)]
+    #[case(&["ecdh-sha2-nistp256", "curve25519-sha256"], &["ssh-ed25519"], false, true, false, true)]
+    #[case(&["curve25519-sha256"], &["rsa-sha2-256", "ssh-ed25519"], false, true, false, true)]
+    #[case(&["curve25519-sha256"], &["ssh-ed25519"], true, true, false, false)]
+    #[case(&["ecdh-sha2-nistp256", "curve25519-sha256"], &["ssh-ed25519"], true, true, true, true)]
+    fn it_detects_wrong_guesses(
+        #[case] kex: &[&str],
+        #[case] host_key: &[&str],
+        #[case] client_follows: bool,
+        #[case] server_follows: bool,
+        #[case] ignore_client: bool,
+        #[case] ignore_server: bool,
+    ) {
+        let mut client = kexinit(
+            &["curve25519-sha256", "ecdh-sha2-nistp256"],
+            &["ssh-ed25519", "rsa-sha2-256"],
+            &["aes128-ctr"],
+            &["hmac-sha2-256"],
+        );
+        client.first_kex_packet_follows = client_follows.into();
+        let mut server = kexinit(kex, host_key, &["aes128-ctr"], &["hmac-sha2-256"]);
+        server.first_kex_packet_follows = server_follows.into();
+
+        let negotiated = KexInit::negotiate(&client, &server).unwrap();
+        assert_eq!(&*negotiated.kex, "curve25519-sha256");
+        assert_eq!(&*negotiated.server_host_key, "ssh-ed25519");
+
+        // Only the packet guessed by the peer is subject to be ignored, by the side receiving it
+        assert_eq!(negotiated.ignore_guessed_kex_packet(&client), ignore_client);
+        assert_eq!(negotiated.ignore_guessed_kex_packet(&server), ignore_server);
+    }
+
+    #[test]
+    fn it_advertises_and_answers_ping() {
+        let ext = ExtInfo::default();
+        assert!(!ext.ping());
+
+        let ext = ext.with_ping().with_ping();
+        assert!(ext.ping());
+        assert_eq!(ext.extensions.len(), 1);
+
+        let ping = Ping {
+            data: arch::Bytes::borrowed(b"liveness"),
+        };
+        assert_eq!(ping.pong().data, ping.data);
+    }
+
+    #[test]
+    fn it_advertises_ext_info() {
+        let kexinit = kexinit(&["curve25519-sha256"], &[], &[], &[]);
+        assert!(!kexinit.ext_info_c());
+
+        let kexinit = kexinit.with_ext_info_c().with_ext_info_c();
+        assert!(kexinit.ext_info_c());
+        assert!(!kexinit.ext_info_s());
+        assert_eq!(&*kexinit.kex_algorithms.0, "curve25519-sha256,ext-info-c");
+    }
+}
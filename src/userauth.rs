@@ -1,5 +1,6 @@
 //! Messages involved in the SSH's **authentication** (`SSH-USERAUTH`) part of the protocol,
-//! as defined in the [RFC 4252](https://datatracker.ietf.org/doc/html/rfc4252) and [RFC 4256](https://datatracker.ietf.org/doc/html/rfc4256).
+//! as defined in the [RFC 4252](https://datatracker.ietf.org/doc/html/rfc4252), [RFC 4256](https://datatracker.ietf.org/doc/html/rfc4256)
+//! and [RFC 4462](https://datatracker.ietf.org/doc/html/rfc4462).
 
 use binrw::binrw;
 
@@ -100,6 +101,18 @@ pub enum Method<'b> {
         /// A hint for the prefered interactive submethod.
         submethods: arch::Utf8<'b>,
     },
+
+    /// Authenticate using the `gssapi-with-mic` method,
+    /// as defined in [RFC4462 section 3.2](https://datatracker.ietf.org/doc/html/rfc4462#section-3.2).
+    #[br(pre_assert(method == Method::GSSAPI_WITH_MIC))]
+    GssapiWithMic {
+        #[bw(calc = mechanisms.len() as u32)]
+        num_mechanisms: u32,
+
+        /// The ASN.1 DER encoded OIDs of the supported mechanisms.
+        #[br(count = num_mechanisms)]
+        mechanisms: Vec<arch::Bytes<'static>>,
+    },
 }
 
 impl Method<'_> {
@@ -118,6 +131,9 @@ impl Method<'_> {
     /// The SSH `keyboard-interactive` authentication method.
     pub const KEYBOARD_INTERACTIVE: arch::Ascii<'static> = arch::ascii!("keyboard-interactive");
 
+    /// The SSH `gssapi-with-mic` authentication method.
+    pub const GSSAPI_WITH_MIC: arch::Ascii<'static> = arch::ascii!("gssapi-with-mic");
+
     /// Get the [`Method`]'s SSH identifier.
     pub fn as_ascii(&self) -> arch::Ascii<'static> {
         match self {
@@ -126,6 +142,7 @@ impl Method<'_> {
             Self::Password { .. } => Self::PASSWORD,
             Self::Hostbased { .. } => Self::HOSTBASED,
             Self::KeyboardInteractive { .. } => Self::KEYBOARD_INTERACTIVE,
+            Self::GssapiWithMic { .. } => Self::GSSAPI_WITH_MIC,
         }
     }
 }
@@ -209,6 +226,78 @@ pub struct InfoResponse {
     pub responses: Vec<arch::Utf8<'static>>,
 }
 
+/// The `SSH_MSG_USERAUTH_GSSAPI_RESPONSE` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-3.3>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 60_u8)]
+pub struct GssapiResponse<'b> {
+    /// The ASN.1 DER encoded OID of the selected mechanism.
+    pub mechanism: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_USERAUTH_GSSAPI_TOKEN` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-3.4>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 61_u8)]
+pub struct GssapiToken<'b> {
+    /// Token produced by the peer's security context routine.
+    pub token: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_USERAUTH_GSSAPI_EXCHANGE_COMPLETE` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-3.5>.
+#[binrw]
+#[derive(Debug, Default, Clone)]
+#[brw(big, magic = 63_u8)]
+pub struct GssapiExchangeComplete;
+
+/// The `SSH_MSG_USERAUTH_GSSAPI_ERROR` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-3.8>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 64_u8)]
+pub struct GssapiError<'b> {
+    /// The GSS-API major status code.
+    pub major_status: u32,
+
+    /// The GSS-API minor status code.
+    pub minor_status: u32,
+
+    /// Human-readable description of the error.
+    pub message: arch::Utf8<'b>,
+
+    /// Language tag.
+    pub language: arch::Ascii<'b>,
+}
+
+/// The `SSH_MSG_USERAUTH_GSSAPI_ERRTOK` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-3.9>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 65_u8)]
+pub struct GssapiErrtok<'b> {
+    /// Error token produced by the server's `GSS_Accept_sec_context()`.
+    pub token: arch::Bytes<'b>,
+}
+
+/// The `SSH_MSG_USERAUTH_GSSAPI_MIC` message.
+///
+/// see <https://datatracker.ietf.org/doc/html/rfc4462#section-3.5>.
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big, magic = 66_u8)]
+pub struct GssapiMic<'b> {
+    /// The MIC of the [`crate::crypto::signature::GssapiWithMic`] data, produced by `GSS_GetMIC()`.
+    pub mic: arch::Bytes<'b>,
+}
+
 /// The `SSH_MSG_USERAUTH_FAILURE` message.
 ///
 /// see <https://datatracker.ietf.org/doc/html/rfc4252#section-5.1>.